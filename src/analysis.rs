@@ -2,18 +2,23 @@ pub mod rule;
 pub mod semantic_token;
 
 use self::{
-    rule::RuleAnalysisResult,
+    rule::{RuleAnalysisResult, RuleLinks},
     semantic_token::{
-        Token, ATOM_LEGEND_TYPE, CONTEXT_LEGEND_TYPE, HYPERLINK_LEGEND_TYPE,
+        Token, ATOM_LEGEND_TYPE, CONS_ATOM_LEGEND_TYPE, CONTEXT_LEGEND_TYPE, HYPERLINK_LEGEND_TYPE,
         KEYWORD_ATOM_LEGEND_TYPE, LINK_LEGEND_TYPE, MEMBRANE_LEGEND_TYPE, NUMBER_ATOM_LEGEND_TYPE,
         OPERATOR_ATOM_LEGEND_TYPE,
     },
 };
 use crate::utils::span_to_range;
-use lmntalc::{frontend::ast::AtomName, util::Span, ASTNode};
+use lmntalc::{
+    frontend::ast::AtomName,
+    util::{Pos, Span},
+    ASTNode,
+};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tower_lsp::lsp_types::{
-    Diagnostic, DiagnosticRelatedInformation, DocumentSymbol, Location, SymbolKind, Url,
+    Diagnostic, DiagnosticRelatedInformation, DocumentSymbol, Location, Range, SymbolKind, Url,
 };
 
 pub use self::semantic_token::LEGEND_TYPE;
@@ -24,7 +29,27 @@ pub struct ProgramInfo {
     pub doc_symbol: Vec<DocumentSymbol>,
     pub diagnostics: Vec<Diagnostic>,
     pub refs: Vec<Vec<Span>>,
+    /// Occurrences of head links in guards, which check the links rather than connect them.
+    pub guard_checks: Vec<Span>,
     pub symbols: Vec<Span>,
+    pub rule_links: Vec<RuleLinks>,
+}
+
+/// The data of a "Free link" diagnostic, used by its quick fixes.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeLinkData {
+    pub name: String,
+    /// The other free links in the same scope.
+    pub free_links: Vec<String>,
+}
+
+/// The data of a "Link occurs more than twice" diagnostic, used by its quick fixes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultiOccurData {
+    pub name: String,
+    /// All of the occurrences of the link.
+    pub occurrences: Vec<Range>,
 }
 
 #[derive(Debug)]
@@ -34,7 +59,9 @@ pub struct Analyzer<'ast> {
     semantic_tokens: Vec<Token>,
     diagnostics: Vec<Diagnostic>,
     refs: Vec<Vec<Span>>,
+    guard_checks: Vec<Span>,
     symbols: Vec<Span>,
+    rule_links: Vec<RuleLinks>,
 }
 
 #[derive(Debug, Default)]
@@ -42,6 +69,7 @@ pub struct AnalysisResult {
     symbols: Vec<DocumentSymbol>,
     link_occurrences: HashMap<String, Vec<Span>>,
     hyperlink_occurrences: HashMap<String, Vec<Span>>,
+    context_occurrences: HashMap<String, Vec<Span>>,
 }
 
 impl<'ast> Analyzer<'ast> {
@@ -52,7 +80,9 @@ impl<'ast> Analyzer<'ast> {
             semantic_tokens: Vec::new(),
             diagnostics: Vec::new(),
             refs: Vec::new(),
+            guard_checks: Vec::new(),
             symbols: Vec::new(),
+            rule_links: Vec::new(),
         }
     }
 
@@ -84,8 +114,10 @@ impl<'ast> Analyzer<'ast> {
             semantic_tokens: self.semantic_tokens,
             doc_symbol: result.symbols,
             refs: self.refs,
+            guard_checks: self.guard_checks,
             symbols: self.symbols,
             diagnostics: self.diagnostics,
+            rule_links: self.rule_links,
         }
     }
 
@@ -115,9 +147,19 @@ impl<'ast> Analyzer<'ast> {
                     AtomName::Keyword(_) => KEYWORD_ATOM_LEGEND_TYPE,
                     AtomName::Operator(_) => OPERATOR_ATOM_LEGEND_TYPE,
                     AtomName::Int(_) | AtomName::Float(_) => NUMBER_ATOM_LEGEND_TYPE,
+                    AtomName::Char('.') => CONS_ATOM_LEGEND_TYPE,
                     _ => ATOM_LEGEND_TYPE,
                 };
-                self.add_symbol(name.1, token_type);
+                let mut span = name.1;
+                if let AtomName::Char(_) = name.0 {
+                    // the span of a character atom misses its opening quote
+                    let low = span.low();
+                    span = Span::new(
+                        Pos::new(low.offset - 1, low.line, low.column - 1),
+                        span.high(),
+                    );
+                }
+                self.add_symbol(span, token_type);
             }
             ASTNode::Link {
                 name,
@@ -152,12 +194,20 @@ impl<'ast> Analyzer<'ast> {
                         .push(*span);
                 }
             }
-            ASTNode::Context { span, .. } => self.add_symbol(*span, CONTEXT_LEGEND_TYPE),
+            ASTNode::Context { name, span } => {
+                self.add_symbol(*span, CONTEXT_LEGEND_TYPE);
+                result
+                    .context_occurrences
+                    .entry(name.clone())
+                    .or_default()
+                    .push(*span);
+            }
             _ => unreachable!(),
         }
         result
     }
 
+    #[allow(deprecated)]
     fn analyze_membrane(&mut self, ast: &ASTNode) -> AnalysisResult {
         if let ASTNode::Membrane {
             name,
@@ -204,7 +254,12 @@ impl<'ast> Analyzer<'ast> {
     }
 
     fn filter_links_top(&mut self, links: HashMap<String, Vec<Span>>) {
-        for (_, occur) in links {
+        let free_links = links
+            .iter()
+            .filter(|(_, occur)| occur.len() == 1)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for (name, occur) in links {
             match occur.len() {
                 0 => {}
                 1 => {
@@ -216,31 +271,44 @@ impl<'ast> Analyzer<'ast> {
                         message: "Free link".to_string(),
                         related_information: None,
                         tags: None,
-                        data: None,
+                        data: serde_json::to_value(FreeLinkData {
+                            free_links: free_links
+                                .iter()
+                                .filter(|other| **other != name)
+                                .cloned()
+                                .collect(),
+                            name,
+                        })
+                        .ok(),
                         code_description: None,
                     });
                 }
                 2 => self.refs.push(occur),
-                _ => self.report_multi_occur(&occur),
+                _ => self.report_multi_occur(&name, &occur),
             }
         }
     }
 
     fn filter_links_inner(&mut self, links: &mut HashMap<String, Vec<Span>>) {
-        links.retain(|_, links| match links.len() {
+        links.retain(|name, links| match links.len() {
             0 | 1 => true,
             2 => {
                 self.refs.push(links.clone());
                 false
             }
             _ => {
-                self.report_multi_occur(links);
+                self.report_multi_occur(name, links);
                 false
             }
         });
     }
 
-    fn report_multi_occur(&mut self, occurs: &[Span]) {
+    fn report_multi_occur(&mut self, name: &str, occurs: &[Span]) {
+        let data = serde_json::to_value(MultiOccurData {
+            name: name.to_owned(),
+            occurrences: occurs.iter().map(|x| span_to_range(*x)).collect(),
+        })
+        .ok();
         let mut occurs = occurs.iter();
         let relate = vec![
             DiagnosticRelatedInformation {
@@ -268,7 +336,7 @@ impl<'ast> Analyzer<'ast> {
                 message: "Link occurs more than twice".to_string(),
                 related_information: Some(relate.clone()),
                 tags: None,
-                data: None,
+                data: data.clone(),
                 code_description: None,
             });
         }
@@ -296,6 +364,12 @@ impl AnalysisResult {
                 .or_default()
                 .extend(occur);
         }
+        for (context, occur) in other.context_occurrences {
+            self.context_occurrences
+                .entry(context)
+                .or_default()
+                .extend(occur);
+        }
         self.symbols.extend(other.symbols);
     }
 
@@ -303,3 +377,40 @@ impl AnalysisResult {
         self.symbols.extend(rule_result.symbols);
     }
 }
+
+#[test]
+fn test_hyperlink_groups() {
+    use lmntalc::util::Source;
+
+    let src = Source::from_string("a(!X), {b(!X)}. c(!X) :- d(!X), e(!X).".to_owned());
+    let tokens = lmntalc::LMNtalLexer::new(&src).lex().tokens;
+    let ast = lmntalc::LMNtalParser::new().parse(tokens).ast;
+    let info = Analyzer::new(Url::parse("file:///test.lmn").unwrap(), &ast).analyze();
+
+    let mut sizes = info.refs.iter().map(Vec::len).collect::<Vec<_>>();
+    sizes.sort();
+    assert_eq!(sizes, vec![2, 3]);
+}
+
+#[test]
+fn test_guard_links() {
+    use lmntalc::util::Source;
+
+    let src = Source::from_string("a(X, Y) :- int(X), Z = X + 1 | b(Y, Z).".to_owned());
+    let tokens = lmntalc::LMNtalLexer::new(&src).lex().tokens;
+    let ast = lmntalc::LMNtalParser::new().parse(tokens).ast;
+    let info = Analyzer::new(Url::parse("file:///test.lmn").unwrap(), &ast).analyze();
+
+    // `X` is free as it is not used in the body, but the guard does not make it occur thrice
+    assert_eq!(
+        info.diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Free link"]
+    );
+    let mut sizes = info.refs.iter().map(Vec::len).collect::<Vec<_>>();
+    sizes.sort();
+    assert_eq!(sizes, vec![2, 2, 3]);
+    assert_eq!(info.guard_checks.len(), 2);
+}
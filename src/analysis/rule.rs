@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use lmntalc::ASTNode;
 use tower_lsp::lsp_types::{DocumentSymbol, Position, Range, SymbolKind};
 
@@ -5,15 +7,27 @@ use crate::utils::span_to_range;
 
 use super::{
     semantic_token::{Token, RULE_LEGEND_TYPE},
-    Analyzer,
+    AnalysisResult, Analyzer,
 };
 
+/// The links of a rule, used to complete links in its body.
+#[derive(Debug, Clone)]
+pub struct RuleLinks {
+    /// The range of the rule, without its name.
+    pub rule: Range,
+    /// The links occurring once in the head, which are to be connected to the body.
+    pub head: Vec<String>,
+    /// The occurrences of the links of the body, along with the ones in `head`.
+    pub occurrences: HashMap<String, Vec<Range>>,
+}
+
 #[derive(Debug, Default)]
 pub(super) struct RuleAnalysisResult {
     pub(super) symbols: Vec<DocumentSymbol>,
 }
 
 impl<'ast> Analyzer<'ast> {
+    #[allow(deprecated)]
     pub(super) fn analyze_rule(&mut self, ast: &ASTNode) -> RuleAnalysisResult {
         if let ASTNode::Rule {
             name,
@@ -52,18 +66,66 @@ impl<'ast> Analyzer<'ast> {
                 result.extend(self.analyze_process_list(propagation, true));
             }
 
+            let head_occurrences = result
+                .link_occurrences
+                .iter()
+                .map(|(name, occur)| (name.clone(), occur[0]))
+                .collect::<HashMap<_, _>>();
             self.filter_links_inner(&mut result.link_occurrences);
+            let head_links = result.link_occurrences.keys().cloned().collect();
 
+            let mut guard_links = HashMap::new();
             if let Some(guard) = guard {
-                self.analyze_guard(guard);
+                let mut guard_result = self.analyze_guard(guard);
+                // links of the head are only inspected by the guard, others are new links
+                // assigned by the guard and used in the body
+                for (name, occur) in std::mem::take(&mut guard_result.link_occurrences) {
+                    match head_occurrences.get(&name) {
+                        Some(head) => {
+                            guard_links.insert(*head, occur);
+                        }
+                        None => {
+                            result.link_occurrences.insert(name, occur);
+                        }
+                    }
+                }
+                result.extend(guard_result);
             }
 
             if let Some(body) = body {
                 result.extend(self.analyze_process_list(body, true));
             }
 
+            self.rule_links.push(RuleLinks {
+                rule: span_to_range(*span),
+                head: head_links,
+                occurrences: result
+                    .link_occurrences
+                    .iter()
+                    .map(|(name, occur)| {
+                        (
+                            name.clone(),
+                            occur.iter().map(|x| span_to_range(*x)).collect(),
+                        )
+                    })
+                    .collect(),
+            });
             self.filter_links_top(result.link_occurrences);
 
+            // join the occurrences in the guard to the references of the link in the head, for
+            // finding references and renaming, but keep them apart from its partner
+            for (head, occur) in guard_links {
+                self.guard_checks.extend(&occur);
+                match self.refs.iter_mut().find(|refs| refs.contains(&head)) {
+                    Some(refs) => refs.extend(occur),
+                    None => self.refs.push([head].into_iter().chain(occur).collect()),
+                }
+            }
+
+            // hyperlinks and contexts are scoped to the rule
+            self.refs.extend(result.hyperlink_occurrences.into_values());
+            self.refs.extend(result.context_occurrences.into_values());
+
             RuleAnalysisResult {
                 symbols: vec![DocumentSymbol {
                     name: name.0.clone(),
@@ -81,5 +143,11 @@ impl<'ast> Analyzer<'ast> {
         }
     }
 
-    fn analyze_guard(&mut self, _guard: &ASTNode) {}
+    /// Analyze the type checks, comparisons and assignments of a guard.
+    ///
+    /// Inspecting a link of the head does not count as an occurrence of it, so the links of the
+    /// result are left for the caller to join with the ones of the head.
+    fn analyze_guard(&mut self, guard: &ASTNode) -> AnalysisResult {
+        self.analyze_process_list(guard, true)
+    }
 }
@@ -12,6 +12,7 @@ pub const LEGEND_TYPE: &[SemanticTokenType] = &[
     SemanticTokenType::STRING,
     SemanticTokenType::NUMBER,
     SemanticTokenType::COMMENT,
+    SemanticTokenType::ENUM_MEMBER, // Cons
 ];
 
 pub const RULE_LEGEND_TYPE: u32 = 0;
@@ -24,6 +25,7 @@ pub const KEYWORD_ATOM_LEGEND_TYPE: u32 = 6;
 pub const OPERATOR_ATOM_LEGEND_TYPE: u32 = 7;
 pub const STRING_ATOM_LEGEND_TYPE: u32 = 8;
 pub const NUMBER_ATOM_LEGEND_TYPE: u32 = 9;
+pub const CONS_ATOM_LEGEND_TYPE: u32 = 11;
 
 #[derive(Debug, Default)]
 pub struct Token {
@@ -1,13 +1,24 @@
 use crate::analysis::semantic_token::to_semantic_tokens;
-use crate::analysis::Analyzer;
+use crate::analysis::{rule::RuleLinks, Analyzer};
 use crate::capabilities;
+use crate::code_action;
+use crate::completion;
 use crate::config::Config;
-use crate::diagnostics::Diagnostics;
+use crate::document::Document;
+use crate::folding_range;
+use crate::format;
+use crate::hover;
+use crate::inlay_hint;
 use crate::reference::RefereceMap;
+use crate::rename;
+use crate::selection_range;
+use crate::signature_help;
 use crate::utils::check_update;
 
 use dashmap::DashMap;
-use lmntalc::util::Source;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
@@ -16,14 +27,27 @@ use tower_lsp::{Client, LanguageServer};
 pub struct Backend {
     client: Client,
     config: RwLock<Config>,
+    workspace_roots: RwLock<Vec<Url>>,
     document_symbol_map: DashMap<Url, Vec<DocumentSymbol>>,
     semantic_token_map: DashMap<Url, Vec<SemanticToken>>,
     reference_map: DashMap<Url, RefereceMap>,
+    rule_links_map: DashMap<Url, Vec<RuleLinks>>,
+    document_map: Arc<DashMap<Url, Document>>,
 }
 
+/// Directories holding build outputs and dependencies, which are not indexed.
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _params: InitializeParams) -> Result<InitializeResult> {
+    #[allow(deprecated)]
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let mut roots = self.workspace_roots.write().await;
+        if let Some(folders) = params.workspace_folders {
+            roots.extend(folders.into_iter().map(|folder| folder.uri));
+        } else if let Some(root) = params.root_uri {
+            roots.push(root);
+        }
         Ok(capabilities::capabilities())
     }
 
@@ -81,6 +105,45 @@ impl LanguageServer for Backend {
                     .await;
             }
         }
+        drop(config);
+
+        let watchers = serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.lmn".to_owned()),
+                kind: None,
+            }],
+        })
+        .ok();
+        if self
+            .client
+            .register_capability(vec![Registration {
+                id: "lmntal-watched-files".to_owned(),
+                method: "workspace/didChangeWatchedFiles".to_owned(),
+                register_options: watchers,
+            }])
+            .await
+            .is_err()
+        {
+            self.client
+                .log_message(MessageType::WARNING, "Failed to watch the LMNtal files.")
+                .await;
+        }
+
+        let roots = self
+            .workspace_roots
+            .read()
+            .await
+            .iter()
+            .filter_map(|root| root.to_file_path().ok())
+            .collect();
+        let documents = self.document_map.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let indexed = index_workspace(roots, &documents).await;
+            client
+                .log_message(MessageType::INFO, format!("Indexed {} files.", indexed))
+                .await;
+        });
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -100,6 +163,16 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            // opened documents are kept up to date by the client
+            if self.reference_map.contains_key(&change.uri) {
+                continue;
+            }
+            self.reindex(change.uri).await;
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         self.on_change(params.text_document).await;
     }
@@ -119,7 +192,17 @@ impl LanguageServer for Backend {
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        _ = params;
+        let uri = params.text_document.uri;
+        self.semantic_token_map.remove(&uri);
+        self.document_symbol_map.remove(&uri);
+        self.reference_map.remove(&uri);
+        self.rule_links_map.remove(&uri);
+        self.client
+            .publish_diagnostics(uri.clone(), vec![], None)
+            .await;
+
+        // keep the file indexed as it is on the disk, for the other documents only
+        self.reindex(uri).await;
     }
 
     async fn document_symbol(
@@ -134,6 +217,120 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let param = params.text_document_position_params;
+        let uri = param.text_document.uri;
+        if let Some(ref_map) = self.reference_map.get(&uri) {
+            let line = param.position.line;
+            let col = param.position.character;
+            Ok(ref_map.query_definition(line, col).map(|def| {
+                GotoDefinitionResponse::Scalar(Location {
+                    uri: uri.clone(),
+                    range: def.into(),
+                })
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let param = params.text_document_position;
+        let uri = param.text_document.uri;
+        if let Some(ref_map) = self.reference_map.get(&uri) {
+            let line = param.position.line;
+            let col = param.position.character;
+            let (Some(mut refs), Some(symbol)) = (
+                ref_map.query_references(line, col),
+                ref_map.query(line, col),
+            ) else {
+                return Ok(None);
+            };
+            refs.push(symbol);
+            if !params.context.include_declaration {
+                if let Some(def) = ref_map.query_definition(line, col) {
+                    refs.retain(|r| *r != def);
+                }
+            }
+            refs.sort();
+            Ok(Some(
+                refs.into_iter()
+                    .map(|r| Location {
+                        uri: uri.clone(),
+                        range: r.into(),
+                    })
+                    .collect(),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri;
+        if let Some(doc) = self.document_map.get(&uri) {
+            Ok(rename::prepare_rename(&doc, params.position))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let param = params.text_document_position;
+        let uri = param.text_document.uri;
+        let functor = match self.document_map.get(&uri) {
+            Some(doc) => rename::functor_at(&doc, param.position),
+            None => return Ok(None),
+        };
+        if let Some(functor) = functor {
+            rename::check_atom_name(&params.new_name)?;
+            // edits to unopened files must match them as they are on the disk
+            let unopened = self
+                .document_map
+                .iter()
+                .map(|doc| doc.key().clone())
+                .filter(|uri| !self.reference_map.contains_key(uri))
+                .collect::<Vec<_>>();
+            for uri in unopened {
+                self.reindex(uri).await;
+            }
+            let mut changes = HashMap::new();
+            for doc in self.document_map.iter() {
+                let edits = rename::rename_functor(doc.value(), &functor, &params.new_name);
+                if !edits.is_empty() {
+                    changes.insert(doc.key().clone(), edits);
+                }
+            }
+            return Ok(Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            }));
+        }
+
+        let (edit, collision) = if let (Some(doc), Some(ref_map)) =
+            (self.document_map.get(&uri), self.reference_map.get(&uri))
+        {
+            (
+                rename::rename(&uri, &doc, &ref_map, param.position, &params.new_name)?,
+                rename::rule_collision(&doc, param.position, &params.new_name),
+            )
+        } else {
+            return Ok(None);
+        };
+        if let Some(message) = collision {
+            self.client
+                .show_message(MessageType::WARNING, message)
+                .await;
+        }
+        Ok(edit)
+    }
+
     async fn document_highlight(
         &self,
         params: DocumentHighlightParams,
@@ -176,18 +373,165 @@ impl LanguageServer for Backend {
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        _ = params;
-        Ok(None)
+        let param = params.text_document_position_params;
+        let uri = param.text_document.uri;
+        if let (Some(doc), Some(ref_map)) =
+            (self.document_map.get(&uri), self.reference_map.get(&uri))
+        {
+            Ok(hover::hover(&doc, &ref_map, param.position))
+        } else {
+            Ok(None)
+        }
     }
 
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
-        _ = params;
-        Ok(None)
+        let uri = params.text_document.uri;
+        if let Some(doc) = self.document_map.get(&uri) {
+            Ok(format::format(&doc, &params.options))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        if let Some(doc) = self.document_map.get(&uri) {
+            Ok(format::format_range(&doc, params.range, &params.options))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let param = params.text_document_position;
+        let uri = param.text_document.uri;
+        if let Some(doc) = self.document_map.get(&uri) {
+            Ok(format::format_on_type(
+                &doc,
+                param.position,
+                &params.options,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let workspace = self
+            .document_map
+            .iter()
+            .filter(|entry| entry.key() != uri)
+            .flat_map(|entry| completion::functors(entry.value()))
+            .collect();
+        if let (Some(doc), Some(rule_links)) =
+            (self.document_map.get(uri), self.rule_links_map.get(uri))
+        {
+            Ok(Some(CompletionResponse::Array(completion::completions(
+                &doc,
+                &rule_links,
+                params.text_document_position.position,
+                &workspace,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let functors = self
+            .document_map
+            .iter()
+            .flat_map(|entry| completion::functors(entry.value()))
+            .collect();
+        if let Some(doc) = self.document_map.get(uri) {
+            Ok(signature_help::signature_help(
+                &doc,
+                params.text_document_position_params.position,
+                &functors,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let nested_arity = self.config.read().await.nested_arity_hints;
+        if let (Some(doc), Some(ref_map)) =
+            (self.document_map.get(&uri), self.reference_map.get(&uri))
+        {
+            Ok(Some(inlay_hint::inlay_hints(
+                &doc,
+                &ref_map,
+                params.range,
+                nested_arity,
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        if let (Some(doc), Some(ref_map)) =
+            (self.document_map.get(&uri), self.reference_map.get(&uri))
+        {
+            Ok(rename::linked_editing_ranges(
+                &doc,
+                &ref_map,
+                params.text_document_position_params.position,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        if let Some(doc) = self.document_map.get(&params.text_document.uri) {
+            Ok(Some(folding_range::folding_ranges(&doc)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        if let Some(doc) = self.document_map.get(&params.text_document.uri) {
+            Ok(Some(selection_range::selection_ranges(
+                &doc,
+                &params.positions,
+            )))
+        } else {
+            Ok(None)
+        }
     }
 
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
-        _ = params;
-        Ok(None)
+        let uri = &params.text_document.uri;
+        if let Some(doc) = self.document_map.get(uri) {
+            let ref_map = self.reference_map.get(uri);
+            Ok(Some(code_action::code_actions(
+                uri,
+                &doc,
+                ref_map.as_deref(),
+                &params,
+            )))
+        } else {
+            Ok(None)
+        }
     }
 }
 
@@ -196,9 +540,30 @@ impl Backend {
         Self {
             client,
             config: RwLock::new(Config::default()),
+            workspace_roots: RwLock::new(Vec::new()),
             document_symbol_map: DashMap::new(),
             semantic_token_map: DashMap::new(),
             reference_map: DashMap::new(),
+            rule_links_map: DashMap::new(),
+            document_map: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Parse the file of an unopened document again as it is on the disk, or drop it if the
+    /// file is gone.
+    async fn reindex(&self, uri: Url) {
+        match uri.to_file_path() {
+            Ok(path) => match tokio::fs::read_to_string(path).await {
+                Ok(text) => {
+                    self.document_map.insert(uri, Document::parse(text).0);
+                }
+                Err(_) => {
+                    self.document_map.remove(&uri);
+                }
+            },
+            Err(_) => {
+                self.document_map.remove(&uri);
+            }
         }
     }
 
@@ -206,23 +571,9 @@ impl Backend {
         let uri = doc.uri;
         let text = doc.text;
 
-        if text.is_empty() {
-            return;
-        }
-        let src = Source::from_string(text);
-        let mut lexer = lmntalc::LMNtalLexer::new(&src);
-        let mut parser = lmntalc::LMNtalParser::new();
-
-        let mut diagnostics = Diagnostics::default();
-        let lexing_result = lexer.lex();
-        diagnostics.extend(lexing_result.errors);
+        let (document, mut diagnostics) = Document::parse(text);
 
-        let parsing_result = parser.parse(lexing_result.tokens);
-        diagnostics.extend(parsing_result.parsing_errors);
-        diagnostics.extend(parsing_result.parsing_warnings);
-
-        let ast = parsing_result.ast;
-        let analyzer = Analyzer::new(uri.clone(), &ast);
+        let analyzer = Analyzer::new(uri.clone(), &document.ast);
         let mut analysis_result = analyzer.analyze();
         let tokens = to_semantic_tokens(&mut analysis_result.semantic_tokens);
 
@@ -230,8 +581,15 @@ impl Backend {
         self.document_symbol_map
             .insert(uri.clone(), analysis_result.doc_symbol);
 
-        let reference_map = RefereceMap::new(analysis_result.refs, analysis_result.symbols);
+        let reference_map = RefereceMap::new(
+            analysis_result.refs,
+            analysis_result.symbols,
+            analysis_result.guard_checks,
+        );
         self.reference_map.insert(uri.clone(), reference_map);
+        self.rule_links_map
+            .insert(uri.clone(), analysis_result.rule_links);
+        self.document_map.insert(uri.clone(), document);
 
         diagnostics.extend(analysis_result.diagnostics);
         self.client
@@ -239,3 +597,37 @@ impl Backend {
             .await;
     }
 }
+
+/// Parse every LMNtal file under the roots that is not opened, returning the number of them.
+async fn index_workspace(mut dirs: Vec<PathBuf>, documents: &DashMap<Url, Document>) -> usize {
+    let mut indexed = 0;
+    while let Some(dir) = dirs.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            if entry.file_type().await.is_ok_and(|ty| ty.is_dir()) {
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "lmn") {
+                let (Ok(uri), Ok(text)) = (
+                    Url::from_file_path(&path),
+                    tokio::fs::read_to_string(&path).await,
+                ) else {
+                    continue;
+                };
+                documents
+                    .entry(uri)
+                    .or_insert_with(|| Document::parse(text).0);
+                indexed += 1;
+            }
+        }
+    }
+    indexed
+}
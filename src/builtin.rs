@@ -0,0 +1,293 @@
+use lmntalc::frontend::ast::AtomName;
+
+/// Documentation of a built-in atom of LMNtal.
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// How the atom is written, using the arguments below.
+    pub signature: &'static str,
+    /// The arguments, prefixed by `+` for inputs and `-` for outputs.
+    pub args: &'static [&'static str],
+    pub doc: &'static str,
+    pub example: &'static str,
+}
+
+impl Builtin {
+    /// The documentation in markdown.
+    pub fn markdown(&self) -> String {
+        format!(
+            "```lmntal\n{}\n```\n\n---\n\n{}\n\n`+` marks an input argument and `-` an output argument.\n\n**Example**\n\n```lmntal\n{}\n```",
+            self.signature, self.doc, self.example
+        )
+    }
+}
+
+/// Built-in atoms the lexer reads as plain identifiers, which are only built in within guards.
+const IDENTIFIER_BUILTINS: &[&str] = &["mod"];
+
+/// Find the documentation of a built-in atom, `in_guard` telling if the atom is in a guard.
+pub fn lookup(name: &AtomName, in_guard: bool) -> Option<&'static Builtin> {
+    let name = match name {
+        AtomName::Keyword(name) => name.clone(),
+        AtomName::Plain(name) if in_guard && IDENTIFIER_BUILTINS.contains(&name.as_str()) => {
+            name.clone()
+        }
+        AtomName::Operator(op) => op.to_string(),
+        _ => return None,
+    };
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    // Type checks
+    Builtin {
+        name: "int",
+        signature: "int(+X)",
+        args: &["+X"],
+        doc: "Succeeds if `X` is connected to an integer atom.",
+        example: "inc(X) :- int(X) | inc(X + 1).",
+    },
+    Builtin {
+        name: "float",
+        signature: "float(+X)",
+        args: &["+X"],
+        doc: "Succeeds if `X` is connected to a floating point number atom.",
+        example: "half(X, Y) :- float(X) | Y = X /. 2.0.",
+    },
+    Builtin {
+        name: "unary",
+        signature: "unary(+X)",
+        args: &["+X"],
+        doc: "Succeeds if `X` is connected to a unary atom, that is an atom with exactly one \
+              argument such as `a`, `3` or `'c'`. The matched atom can be copied in the body.",
+        example: "dup(X, Y, Z) :- unary(X) | Y = X, Z = X.",
+    },
+    Builtin {
+        name: "ground",
+        signature: "ground(+X)",
+        args: &["+X"],
+        doc: "Succeeds if `X` is connected to a ground graph, a connected graph of atoms whose \
+              only free link is `X`. The graph can be copied or removed in the body.",
+        example: "copy(X, Y, Z) :- ground(X) | Y = X, Z = X.",
+    },
+    Builtin {
+        name: "hlink",
+        signature: "hlink(+X)",
+        args: &["+X"],
+        doc: "Succeeds if `X` is connected to a hyperlink.",
+        example: "a(X) :- hlink(X) | b(X).",
+    },
+    Builtin {
+        name: "uniq",
+        signature: "uniq(+X, ...)",
+        args: &["+X"],
+        doc: "Succeeds only the first time the rule is applied to the structures connected to \
+              the arguments, preventing the rule from firing twice on the same structures.",
+        example: "a(X) :- uniq(X) | b(X).",
+    },
+    // Connector
+    Builtin {
+        name: "=",
+        signature: "X = Y",
+        args: &["X", "Y"],
+        doc: "Connects the links `X` and `Y`. In a guard, `-Z = +X + +Y` assigns the result of \
+              an arithmetic expression to a new link `Z` used in the body.",
+        example: "a(X), b(Y) :- X = Y.",
+    },
+    // Integer comparison
+    Builtin {
+        name: "=:=",
+        signature: "+X =:= +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integers `X` and `Y` are equal.",
+        example: "same(X, Y) :- X =:= Y | ok.",
+    },
+    Builtin {
+        name: "=\\=",
+        signature: "+X =\\= +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integers `X` and `Y` are not equal.",
+        example: "diff(X, Y) :- X =\\= Y | ok.",
+    },
+    Builtin {
+        name: "<",
+        signature: "+X < +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integer `X` is less than `Y`.",
+        example: "min(X, Y, Z) :- X < Y | Z = X.",
+    },
+    Builtin {
+        name: ">",
+        signature: "+X > +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integer `X` is greater than `Y`.",
+        example: "max(X, Y, Z) :- X > Y | Z = X.",
+    },
+    Builtin {
+        name: "<=",
+        signature: "+X <= +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integer `X` is less than or equal to `Y`.",
+        example: "min(X, Y, Z) :- X <= Y | Z = X.",
+    },
+    Builtin {
+        name: ">=",
+        signature: "+X >= +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the integer `X` is greater than or equal to `Y`.",
+        example: "max(X, Y, Z) :- X >= Y | Z = X.",
+    },
+    // Float comparison
+    Builtin {
+        name: "=:=.",
+        signature: "+X =:=. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point numbers `X` and `Y` are equal.",
+        example: "same(X, Y) :- X =:=. Y | ok.",
+    },
+    Builtin {
+        name: "=\\=.",
+        signature: "+X =\\=. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point numbers `X` and `Y` are not equal.",
+        example: "diff(X, Y) :- X =\\=. Y | ok.",
+    },
+    Builtin {
+        name: "<.",
+        signature: "+X <. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point number `X` is less than `Y`.",
+        example: "min(X, Y, Z) :- X <. Y | Z = X.",
+    },
+    Builtin {
+        name: ">.",
+        signature: "+X >. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point number `X` is greater than `Y`.",
+        example: "max(X, Y, Z) :- X >. Y | Z = X.",
+    },
+    Builtin {
+        name: "<=.",
+        signature: "+X <=. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point number `X` is less than or equal to `Y`.",
+        example: "min(X, Y, Z) :- X <=. Y | Z = X.",
+    },
+    Builtin {
+        name: ">=.",
+        signature: "+X >=. +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if the floating point number `X` is greater than or equal to `Y`.",
+        example: "max(X, Y, Z) :- X >=. Y | Z = X.",
+    },
+    // Structural comparison
+    Builtin {
+        name: "==",
+        signature: "+X == +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if `X` and `Y` are connected to isomorphic ground graphs.",
+        example: "same(X, Y) :- X == Y | ok.",
+    },
+    Builtin {
+        name: "\\=",
+        signature: "+X \\= +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if `X` and `Y` are connected to ground graphs that are not \
+              isomorphic.",
+        example: "diff(X, Y) :- X \\= Y | ok.",
+    },
+    Builtin {
+        name: "===",
+        signature: "+X === +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if `X` and `Y` are connected to the same unary atom.",
+        example: "same(X, Y) :- X === Y | ok.",
+    },
+    Builtin {
+        name: "\\==",
+        signature: "+X \\== +Y",
+        args: &["+X", "+Y"],
+        doc: "Guard succeeding if `X` and `Y` are connected to different unary atoms.",
+        example: "diff(X, Y) :- X \\== Y | ok.",
+    },
+    // Integer arithmetic
+    Builtin {
+        name: "+",
+        signature: "-Z = +X + +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer addition. Also written as the prefix `+X`.",
+        example: "inc(X, Y) :- int(X) | Y = X + 1.",
+    },
+    Builtin {
+        name: "-",
+        signature: "-Z = +X - +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer subtraction. The prefix `-X` negates `X`.",
+        example: "dec(X, Y) :- int(X) | Y = X - 1.",
+    },
+    Builtin {
+        name: "*",
+        signature: "-Z = +X * +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer multiplication.",
+        example: "square(X, Y) :- int(X) | Y = X * X.",
+    },
+    Builtin {
+        name: "/",
+        signature: "-Z = +X / +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer division, rounded towards zero.",
+        example: "half(X, Y) :- int(X) | Y = X / 2.",
+    },
+    Builtin {
+        name: "%",
+        signature: "-Z = +X % +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer remainder of `X` divided by `Y`.",
+        example: "parity(X, Y) :- int(X) | Y = X % 2.",
+    },
+    Builtin {
+        name: "mod",
+        signature: "mod(+X, +Y, -Z)",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Integer remainder of `X` divided by `Y`, the same as `Z = X % Y`.",
+        example: "parity(X, Y) :- int(X), mod(X, 2, Z) | Y = Z.",
+    },
+    // Float arithmetic
+    Builtin {
+        name: "+.",
+        signature: "-Z = +X +. +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Floating point addition.",
+        example: "inc(X, Y) :- float(X) | Y = X +. 1.0.",
+    },
+    Builtin {
+        name: "-.",
+        signature: "-Z = +X -. +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Floating point subtraction.",
+        example: "dec(X, Y) :- float(X) | Y = X -. 1.0.",
+    },
+    Builtin {
+        name: "*.",
+        signature: "-Z = +X *. +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Floating point multiplication.",
+        example: "square(X, Y) :- float(X) | Y = X *. X.",
+    },
+    Builtin {
+        name: "/.",
+        signature: "-Z = +X /. +Y",
+        args: &["+X", "+Y", "-Z"],
+        doc: "Floating point division.",
+        example: "half(X, Y) :- float(X) | Y = X /. 2.0.",
+    },
+    // Hyperlinks
+    Builtin {
+        name: "><",
+        signature: "!X >< !Y",
+        args: &["!X", "!Y"],
+        doc: "Fuses the hyperlinks `!X` and `!Y` into a single hyperlink.",
+        example: "merge(!X, !Y) :- !X >< !Y.",
+    },
+];
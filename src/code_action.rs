@@ -0,0 +1,129 @@
+pub mod extract_membrane;
+pub mod free_link;
+pub mod hyperlink;
+pub mod list;
+pub mod missing_comma;
+pub mod multi_occur;
+pub mod nesting;
+pub mod rule_skeleton;
+
+use std::collections::HashMap;
+
+use lmntalc::ASTNode;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, Diagnostic, Position, Range,
+    TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{document::Document, reference::RefereceMap, utils::to_position};
+
+pub fn code_actions(
+    uri: &Url,
+    doc: &Document,
+    ref_map: Option<&RefereceMap>,
+    params: &CodeActionParams,
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = vec![];
+    if requested(params, &CodeActionKind::QUICKFIX) {
+        for diagnostic in &params.context.diagnostics {
+            match diagnostic.message.as_str() {
+                "Free link" => actions.extend(free_link::quick_fixes(uri, doc, diagnostic)),
+                "Link occurs more than twice" => {
+                    actions.extend(multi_occur::quick_fixes(uri, doc, diagnostic))
+                }
+                "Missing comma between processes" => {
+                    actions.extend(missing_comma::quick_fix_for(uri, doc, diagnostic))
+                }
+                _ => {}
+            }
+        }
+    }
+    if requested(params, &CodeActionKind::REFACTOR) {
+        actions.extend(rule_skeleton::create_rule(uri, doc, params.range.start));
+    }
+    if requested(params, &CodeActionKind::REFACTOR_EXTRACT) {
+        if let Some(ref_map) = ref_map {
+            actions.extend(extract_membrane::extract_membrane(
+                uri,
+                doc,
+                ref_map,
+                params.range,
+            ));
+        }
+        actions.extend(nesting::flatten(uri, doc, params.range.start));
+    }
+    if requested(params, &CodeActionKind::REFACTOR_INLINE) {
+        actions.extend(nesting::nest(uri, doc, params.range.start));
+    }
+    if requested(params, &CodeActionKind::REFACTOR_REWRITE) {
+        actions.extend(list::list_to_cons(uri, doc, params.range.start));
+        actions.extend(list::cons_to_list(uri, doc, params.range.start));
+        if let Some(ref_map) = ref_map {
+            actions.extend(hyperlink::toggle_hyperlink(
+                uri,
+                doc,
+                ref_map,
+                params.range.start,
+            ));
+        }
+    }
+    // only offered along with the quick fixes if there are several commas to insert
+    if requested(params, &CodeActionKind::SOURCE_FIX_ALL)
+        && (params.context.only.is_some() || doc.missing_commas.len() > 1)
+    {
+        actions.extend(missing_comma::fix_all(uri, doc));
+    }
+    actions
+        .into_iter()
+        .map(CodeActionOrCommand::CodeAction)
+        .collect()
+}
+
+/// Check if the client asks for code actions of the kind.
+fn requested(params: &CodeActionParams, kind: &CodeActionKind) -> bool {
+    params.context.only.as_ref().is_none_or(|only| {
+        only.iter().any(|only| {
+            kind.as_str() == only.as_str()
+                || kind.as_str().starts_with(&format!("{}.", only.as_str()))
+        })
+    })
+}
+
+/// A quick fix for the diagnostic applying the edits to the document.
+fn quick_fix(
+    title: String,
+    uri: &Url,
+    diagnostic: &Diagnostic,
+    edits: Vec<TextEdit>,
+) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Where to append processes related to the position, the end of the body of its rule or of
+/// the process list containing it.
+///
+/// A rule with an empty body has nowhere to append to, since the head must not be changed.
+fn append_position(doc: &Document, range: Range) -> Option<Position> {
+    let path = doc.path_at(range.start);
+    let list = match path
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Rule { .. }))
+    {
+        Some(ASTNode::Rule { body, .. }) => body.as_deref()?,
+        _ => *path
+            .iter()
+            .rev()
+            .find(|node| matches!(node, ASTNode::ProcessList { .. }))?,
+    };
+    Some(to_position(doc.extent(list).high()))
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeSet, HashMap};
+
+use lmntalc::ASTNode;
+use tower_lsp::lsp_types::{CodeAction, CodeActionKind, Range, TextEdit, Url, WorkspaceEdit};
+
+use crate::{
+    document::Document,
+    reference::RefereceMap,
+    syntax::visit_links,
+    utils::{contains, span_to_range, to_position},
+};
+
+/// Wrap the processes of a process list overlapping the range in a new membrane.
+///
+/// Guards cannot contain membranes, so nothing is extracted from them.
+pub fn extract_membrane(
+    uri: &Url,
+    doc: &Document,
+    ref_map: &RefereceMap,
+    range: Range,
+) -> Option<CodeAction> {
+    let (low, high) = trim(doc, range)?;
+    let (start, end) = (doc.position_at(low), doc.position_at(high));
+    let path = doc.path_at(start);
+    let index = path.iter().rposition(|node| {
+        matches!(node, ASTNode::ProcessList { .. }) && contains(doc.extent(node), end)
+    })?;
+    let ASTNode::ProcessList { processes, .. } = path[index] else {
+        unreachable!()
+    };
+    if path[..index].iter().any(|node| {
+        matches!(node, ASTNode::Rule { guard: Some(guard), .. } if std::ptr::eq(guard.as_ref(), path[index]))
+    }) {
+        return None;
+    }
+
+    let selected = processes
+        .iter()
+        .map(|process| doc.extent(process))
+        .filter(|extent| {
+            (extent.low().offset as usize) < high && low < extent.high().offset as usize
+        })
+        .collect::<Vec<_>>();
+    let extent = selected.first()?.merge(*selected.last()?);
+
+    // links inside the new membrane connected to occurrences outside of it
+    let mut crossing = BTreeSet::new();
+    for process in processes {
+        visit_links(process, &mut |link| {
+            if let ASTNode::Link {
+                name,
+                hyperlink: false,
+                span,
+            } = link
+            {
+                let start = to_position(span.low());
+                if extent.contains(*span)
+                    && ref_map
+                        .query_references(start.line, start.character)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .any(|other| !contains(extent, Range::from(other).start))
+                {
+                    crossing.insert(name.as_str());
+                }
+            }
+        });
+    }
+    let crossing = crossing
+        .into_iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+
+    Some(CodeAction {
+        title: if crossing.is_empty() {
+            "Extract into membrane".to_owned()
+        } else {
+            format!(
+                "Extract into membrane, connected by {} across the boundary",
+                crossing.join(", ")
+            )
+        },
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(
+                uri.clone(),
+                vec![TextEdit {
+                    range: span_to_range(extent),
+                    new_text: format!("{{{}}}", doc.slice(extent)),
+                }],
+            )])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// The character offsets of a non-empty range without the spaces around it.
+fn trim(doc: &Document, range: Range) -> Option<(usize, usize)> {
+    let chars = doc.text().chars().collect::<Vec<_>>();
+    let (mut low, mut high) = (doc.offset_at(range.start), doc.offset_at(range.end));
+    high = high.min(chars.len());
+    while low < high && chars[low].is_whitespace() {
+        low += 1;
+    }
+    while low < high && chars[high - 1].is_whitespace() {
+        high -= 1;
+    }
+    (low < high).then_some((low, high))
+}
+
+#[test]
+fn test_extract_membrane() {
+    use crate::document::analyzed;
+    use tower_lsp::lsp_types::Position;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _, ref_map) =
+        analyzed("a(X), b(X, Y), c(Y, Z), d(Z). e :- f(W), g(W). h(V) :- int(V) | i(V).");
+    let at = |character| Position { line: 0, character };
+    let extract = |start, end| {
+        extract_membrane(
+            &uri,
+            &doc,
+            &ref_map,
+            Range {
+                start: at(start),
+                end: at(end),
+            },
+        )
+    };
+
+    let action = extract(6, 22).unwrap();
+    assert_eq!(
+        action.title,
+        "Extract into membrane, connected by `X`, `Z` across the boundary"
+    );
+    let edit = &action.edit.unwrap().changes.unwrap()[&uri][0];
+    assert_eq!(edit.new_text, "{b(X, Y), c(Y, Z)}");
+    assert_eq!((edit.range.start, edit.range.end), (at(6), at(22)));
+
+    assert_eq!(extract(35, 45).unwrap().title, "Extract into membrane");
+    assert!(extract(5, 6).is_none());
+    // guards cannot contain membranes
+    assert!(extract(55, 61).is_none());
+    assert_eq!(
+        extract(64, 68).unwrap().title,
+        "Extract into membrane, connected by `V` across the boundary"
+    );
+
+    // the links of the nested membrane are not connected across the boundary
+    let (doc, _, ref_map) = analyzed("a(X), b(X), {c(X), d(X)}.");
+    let range = Range {
+        start: at(13),
+        end: at(23),
+    };
+    assert_eq!(
+        extract_membrane(&uri, &doc, &ref_map, range).unwrap().title,
+        "Extract into membrane"
+    );
+}
@@ -0,0 +1,148 @@
+use tower_lsp::lsp_types::{CodeAction, Diagnostic, Range, TextEdit, Url};
+
+use crate::{analysis::FreeLinkData, document::Document, syntax::is_atom_name};
+
+use super::{append_position, quick_fix};
+
+/// Links at most this many edits away are offered as renames.
+const MAX_DISTANCE: usize = 2;
+
+/// Quick fixes for a link occurring only once in its scope.
+pub fn quick_fixes(uri: &Url, doc: &Document, diagnostic: &Diagnostic) -> Vec<CodeAction> {
+    let Some(data) = diagnostic
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<FreeLinkData>(data).ok())
+    else {
+        return vec![];
+    };
+    let name = &data.name;
+    let range = diagnostic.range;
+    let mut actions = vec![];
+
+    let mut similar = data
+        .free_links
+        .iter()
+        .map(|other| (edit_distance(name, other), other))
+        .filter(|(distance, _)| *distance <= MAX_DISTANCE)
+        .collect::<Vec<_>>();
+    similar.sort();
+    for (i, (_, other)) in similar.iter().take(3).enumerate() {
+        let mut action = quick_fix(
+            format!("Rename to `{}`", other),
+            uri,
+            diagnostic,
+            vec![TextEdit {
+                range,
+                new_text: other.to_string(),
+            }],
+        );
+        action.is_preferred = Some(i == 0);
+        actions.push(action);
+    }
+
+    actions.push(quick_fix(
+        format!("Convert `{}` to hyperlink `!{}`", name, name),
+        uri,
+        diagnostic,
+        vec![TextEdit {
+            range: Range {
+                start: range.start,
+                end: range.start,
+            },
+            new_text: "!".to_owned(),
+        }],
+    ));
+
+    if let Some(end) = append_position(doc, range) {
+        let (title, text) = match similar.first() {
+            Some((_, other)) => (
+                format!("Connect `{}` to `{}`", name, other),
+                format!(", {} = {}", name, other),
+            ),
+            None => {
+                let atom = match name.to_lowercase() {
+                    lower if is_atom_name(&lower) => lower,
+                    _ => "atom".to_owned(),
+                };
+                (
+                    format!("Add partner atom `{}({})`", atom, name),
+                    format!(", {}({})", atom, name),
+                )
+            }
+        };
+        actions.push(quick_fix(
+            title,
+            uri,
+            diagnostic,
+            vec![TextEdit {
+                range: Range { start: end, end },
+                new_text: text,
+            }],
+        ));
+    }
+
+    actions
+}
+
+/// The Levenshtein distance between two names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (previous + (ca != *cb) as usize)
+                .min(row[j] + 1)
+                .min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[test]
+fn test_free_link_fixes() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, info, _) = analyzed("a(Link1), b(Link2), e(Far). c(X) :- d. f(Y) :- .");
+    let titles = |character| {
+        let diagnostic = info
+            .diagnostics
+            .iter()
+            .find(|d| d.message == "Free link" && d.range.start.character == character)
+            .unwrap();
+        quick_fixes(&uri, &doc, diagnostic)
+            .into_iter()
+            .map(|action| action.title)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        titles(2),
+        vec![
+            "Rename to `Link2`",
+            "Convert `Link1` to hyperlink `!Link1`",
+            "Connect `Link1` to `Link2`"
+        ]
+    );
+    // `Link1` and `Link2` are too far from `Far` to connect
+    assert_eq!(
+        titles(22),
+        vec![
+            "Convert `Far` to hyperlink `!Far`",
+            "Add partner atom `far(Far)`"
+        ]
+    );
+    // `X` is alone in the rule
+    assert_eq!(
+        titles(30),
+        vec!["Convert `X` to hyperlink `!X`", "Add partner atom `x(X)`"]
+    );
+    // the partner is never added to the head
+    assert_eq!(titles(41), vec!["Convert `Y` to hyperlink `!Y`"]);
+    assert_eq!(edit_distance("Link1", "Lnk12"), 2);
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use lmntalc::ASTNode;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionDisabled, CodeActionKind, Position, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    document::Document,
+    reference::RefereceMap,
+    syntax::{fresh_name, link_names, link_occurrences, link_scope},
+};
+
+/// Convert every occurrence of the link at the position to a hyperlink, or of the hyperlink to a
+/// link.
+///
+/// A hyperlink occurring more than twice cannot be a link, so the action is disabled then.
+pub fn toggle_hyperlink(
+    uri: &Url,
+    doc: &Document,
+    ref_map: &RefereceMap,
+    position: Position,
+) -> Option<CodeAction> {
+    let path = doc.path_at(position);
+    let Some(ASTNode::Link {
+        name, hyperlink, ..
+    }) = path.last()
+    else {
+        return None;
+    };
+    let occurrences = link_occurrences(ref_map, &path);
+
+    // the converted name must not be taken in the scope it moves to
+    let scope = link_scope(doc, &path, &occurrences, !hyperlink);
+    let taken = link_names(scope, !hyperlink);
+    let new_name = if taken.contains(name.as_str()) {
+        fresh_name(name, &taken)
+    } else {
+        name.clone()
+    };
+    let (title, new_text) = if *hyperlink {
+        (
+            format!("Convert hyperlink `!{}` to link `{}`", name, new_name),
+            new_name,
+        )
+    } else {
+        (
+            format!("Convert `{}` to hyperlink `!{}`", name, new_name),
+            format!("!{}", new_name),
+        )
+    };
+
+    if *hyperlink && occurrences.len() > 2 {
+        return Some(CodeAction {
+            title,
+            kind: Some(CodeActionKind::REFACTOR_REWRITE),
+            disabled: Some(CodeActionDisabled {
+                reason: format!(
+                    "`!{}` occurs {} times, but a link can only occur twice",
+                    name,
+                    occurrences.len()
+                ),
+            }),
+            ..Default::default()
+        });
+    }
+
+    let edits = occurrences
+        .into_iter()
+        .map(|range| TextEdit {
+            range,
+            new_text: new_text.clone(),
+        })
+        .collect();
+    Some(CodeAction {
+        title,
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_toggle_hyperlink() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _, ref_map) =
+        analyzed("a(X), b(X), c(!X). d(!H), e(!H), f(!H). g(!Y), h(!Y). k(Z), l(Z), m(Z). n(F).");
+    let at = |character| Position { line: 0, character };
+    let toggle = |character| toggle_hyperlink(&uri, &doc, &ref_map, at(character)).unwrap();
+    let new_texts = |action: CodeAction| {
+        let mut edits = action.edit.unwrap().changes.unwrap().remove(&uri).unwrap();
+        edits.sort_by_key(|edit| edit.range.start);
+        edits
+            .into_iter()
+            .map(|edit| (edit.range.start.character, edit.new_text))
+            .collect::<Vec<_>>()
+    };
+
+    let action = toggle(2);
+    assert_eq!(action.title, "Convert `X` to hyperlink `!X1`");
+    assert_eq!(
+        new_texts(action),
+        vec![(2, "!X1".to_owned()), (8, "!X1".to_owned())]
+    );
+
+    let action = toggle(22);
+    assert!(action.disabled.is_some() && action.edit.is_none());
+
+    let action = toggle(43);
+    assert_eq!(action.title, "Convert hyperlink `!Y` to link `Y`");
+    assert_eq!(
+        new_texts(action),
+        vec![(42, "Y".to_owned()), (49, "Y".to_owned())]
+    );
+
+    // links without a partner convert every occurrence of their name
+    let action = toggle(62);
+    assert_eq!(
+        new_texts(action),
+        vec![
+            (56, "!Z".to_owned()),
+            (62, "!Z".to_owned()),
+            (68, "!Z".to_owned())
+        ]
+    );
+    assert_eq!(new_texts(toggle(74)), vec![(74, "!F".to_owned())]);
+
+    assert!(toggle_hyperlink(&uri, &doc, &ref_map, at(0)).is_none());
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use lmntalc::{frontend::ast::AtomName, frontend::token::TokenKind, ASTNode, Token};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    document::Document,
+    utils::{span_to_range, to_position},
+};
+
+fn rewrite(title: &str, uri: &Url, range: Range, new_text: String) -> CodeAction {
+    CodeAction {
+        title: title.to_owned(),
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(
+                uri.clone(),
+                vec![TextEdit { range, new_text }],
+            )])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Rewrite the outermost list literal around the position, such as `[a, b | T]`, with cons
+/// atoms, such as `'.'(a, '.'(b, T))`.
+pub fn list_to_cons(uri: &Url, doc: &Document, position: Position) -> Option<CodeAction> {
+    let offset = doc.offset_at(position) as u32;
+    // the lists around the position are nested in the one opened first
+    let (start, _) = doc
+        .lists()
+        .into_iter()
+        .filter(|&(start, end)| {
+            doc.tokens[start].span.low().offset <= offset
+                && offset <= doc.tokens[end].span.high().offset
+        })
+        .min()?;
+
+    let chars = doc.text().chars().collect::<Vec<_>>();
+    let (end, text) = cons(&chars, &doc.tokens, start)?;
+    Some(rewrite(
+        "Convert list to cons atoms",
+        uri,
+        Range {
+            start: to_position(doc.tokens[start].span.low()),
+            end: to_position(doc.tokens[end].span.high()),
+        },
+        text,
+    ))
+}
+
+/// The cons atoms of the list literal opened by the token at `open`, along with the index of
+/// the closing token.
+fn cons(chars: &[char], tokens: &[Token], open: usize) -> Option<(usize, String)> {
+    let mut depth = 0;
+    let mut separators = vec![open];
+    let mut bar = None;
+    let mut close = None;
+    for (i, token) in tokens.iter().enumerate().skip(open + 1) {
+        match token.kind {
+            TokenKind::LeftBracket | TokenKind::LeftParen | TokenKind::LeftBrace => depth += 1,
+            TokenKind::RightBracket if depth == 0 => {
+                close = Some(i);
+                break;
+            }
+            TokenKind::RightBracket | TokenKind::RightParen | TokenKind::RightBrace => depth -= 1,
+            TokenKind::Comma if depth == 0 && bar.is_none() => separators.push(i),
+            TokenKind::Vert if depth == 0 && bar.is_none() => {
+                bar = Some(separators.len());
+                separators.push(i);
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    separators.push(close);
+
+    let mut elements = separators
+        .windows(2)
+        .map(|pair| {
+            let low = tokens[pair[0]].span.high().offset as usize;
+            let high = tokens[pair[1]].span.low().offset as usize;
+            replace_lists(chars, tokens, low, high).map(|text| text.trim().to_owned())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if elements.len() == 1 && elements[0].is_empty() {
+        // the empty list is an atom of its own
+        return Some((close, "[]".to_owned()));
+    }
+    if elements.iter().any(String::is_empty) {
+        return None;
+    }
+    let mut list = match bar {
+        Some(_) => elements.pop()?,
+        None => "[]".to_owned(),
+    };
+    for element in elements.into_iter().rev() {
+        list = format!("'.'({}, {})", element, list);
+    }
+    Some((close, list))
+}
+
+/// The text between the offsets, with the list literals in it rewritten with cons atoms.
+fn replace_lists(chars: &[char], tokens: &[Token], low: usize, high: usize) -> Option<String> {
+    let mut text = String::new();
+    let mut copied = low;
+    let mut i = tokens.partition_point(|token| (token.span.low().offset as usize) < low);
+    while i < tokens.len() && tokens[i].span.high().offset as usize <= high {
+        if tokens[i].kind == TokenKind::LeftBracket {
+            let (close, list) = cons(chars, tokens, i)?;
+            text.extend(&chars[copied..tokens[i].span.low().offset as usize]);
+            text.push_str(&list);
+            copied = tokens[close].span.high().offset as usize;
+            i = close;
+        }
+        i += 1;
+    }
+    text.extend(&chars[copied..high]);
+    Some(text)
+}
+
+/// Check if the node is a cons atom, which is nested with two arguments or written with all of
+/// its three arguments.
+fn is_cons(node: &ASTNode, nested: bool) -> bool {
+    matches!(
+        node,
+        ASTNode::Atom { name: (AtomName::Char('.'), _), args, .. }
+            if args.len() == if nested { 2 } else { 3 }
+    )
+}
+
+/// Rewrite the outermost chain of cons atoms around the position with a list literal.
+pub fn cons_to_list(uri: &Url, doc: &Document, position: Position) -> Option<CodeAction> {
+    let path = doc.path_at(position);
+    let index = (1..path.len()).find(|&i| {
+        let nested = matches!(path[i - 1], ASTNode::Atom { .. });
+        is_cons(path[i], nested)
+    })?;
+    let ASTNode::Atom { args, .. } = path[index] else {
+        unreachable!()
+    };
+
+    let text = |node| doc.slice(doc.extent(node));
+    let mut elements = vec![text(&args[0])];
+    let mut tail = &args[1];
+    while is_cons(tail, true) {
+        let ASTNode::Atom { args, .. } = tail else {
+            unreachable!()
+        };
+        elements.push(text(&args[0]));
+        tail = &args[1];
+    }
+    let list = format!("[{} | {}]", elements.join(", "), text(tail));
+    let new_text = match args.get(2) {
+        Some(link) => format!("{} = {}", text(link), list),
+        None => list,
+    };
+    Some(rewrite(
+        "Convert cons atoms to list",
+        uri,
+        span_to_range(doc.extent(path[index])),
+        new_text,
+    ))
+}
+
+#[test]
+fn test_list_conversion() {
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let at = |character| Position { line: 0, character };
+    let new_text = |action: Option<CodeAction>| {
+        action.unwrap().edit.unwrap().changes.unwrap()[&uri][0]
+            .new_text
+            .clone()
+    };
+
+    let (doc, _) = Document::parse("a([b, f(c) | T], [d, [e], []]).".to_owned());
+    assert_eq!(
+        new_text(list_to_cons(&uri, &doc, at(3))),
+        "'.'(b, '.'(f(c), T))"
+    );
+    assert_eq!(
+        new_text(list_to_cons(&uri, &doc, at(22))),
+        "'.'(d, '.'('.'(e, []), '.'([], [])))"
+    );
+    assert!(list_to_cons(&uri, &doc, at(0)).is_none());
+
+    let (doc, _) = Document::parse("a('.'(b, '.'(c, T))), '.'(x, U, L), f(L).".to_owned());
+    assert_eq!(new_text(cons_to_list(&uri, &doc, at(13))), "[b, c | T]");
+    assert_eq!(new_text(cons_to_list(&uri, &doc, at(23))), "L = [x | U]");
+    assert!(cons_to_list(&uri, &doc, at(0)).is_none());
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Diagnostic, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{document::Document, utils::to_position};
+
+use super::quick_fix;
+
+fn insert_comma(position: Position) -> TextEdit {
+    TextEdit {
+        range: Range {
+            start: position,
+            end: position,
+        },
+        new_text: ",".to_owned(),
+    }
+}
+
+pub fn quick_fix_for(uri: &Url, doc: &Document, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let position = doc.comma_position(diagnostic.range.start)?;
+    let mut action = quick_fix(
+        "Insert missing comma".to_owned(),
+        uri,
+        diagnostic,
+        vec![insert_comma(position)],
+    );
+    action.is_preferred = Some(true);
+    Some(action)
+}
+
+/// Insert every missing comma in the document at once.
+pub fn fix_all(uri: &Url, doc: &Document) -> Option<CodeAction> {
+    if doc.missing_commas.is_empty() {
+        return None;
+    }
+    let edits = doc
+        .missing_commas
+        .iter()
+        .filter_map(|span| doc.comma_position(to_position(span.low())))
+        .map(insert_comma)
+        .collect();
+    Some(CodeAction {
+        title: "Insert all missing commas".to_owned(),
+        kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_missing_comma_fixes() {
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, diagnostics) = Document::parse("a(X) b(X) {c}.\nd  e.".to_owned());
+    let warnings = diagnostics
+        .diagnostics
+        .iter()
+        .filter(|d| d.message == "Missing comma between processes")
+        .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 3);
+
+    let action = quick_fix_for(&uri, &doc, warnings[0]).unwrap();
+    let edits = &action.edit.unwrap().changes.unwrap()[&uri];
+    assert_eq!(edits[0].range.start, Position::new(0, 4));
+
+    let action = fix_all(&uri, &doc).unwrap();
+    let edits = &action.edit.unwrap().changes.unwrap()[&uri];
+    assert_eq!(
+        edits.iter().map(|e| e.range.start).collect::<Vec<_>>(),
+        vec![
+            Position::new(0, 4),
+            Position::new(0, 9),
+            Position::new(1, 1)
+        ]
+    );
+}
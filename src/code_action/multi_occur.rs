@@ -0,0 +1,132 @@
+use tower_lsp::lsp_types::{CodeAction, Diagnostic, Range, TextEdit, Url};
+
+use crate::{
+    analysis::MultiOccurData,
+    document::Document,
+    syntax::{fresh_name, link_names, link_scope},
+};
+
+use super::{append_position, quick_fix};
+
+/// Quick fixes for an occurrence of a link after its first two.
+pub fn quick_fixes(uri: &Url, doc: &Document, diagnostic: &Diagnostic) -> Vec<CodeAction> {
+    let Some(data) = diagnostic
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<MultiOccurData>(data).ok())
+    else {
+        return vec![];
+    };
+    let name = &data.name;
+    let range = diagnostic.range;
+    let path = doc.path_at(range.start);
+    let mut actions = vec![];
+
+    // the extra occurrence and the one before it get fresh links joined by a connector, which
+    // leaves the first occurrence for the user to pair
+    let previous = data
+        .occurrences
+        .iter()
+        .take_while(|occurrence| **occurrence != range)
+        .last();
+    if let (Some(previous), Some(end)) = (previous, append_position(doc, range)) {
+        let scope = link_scope(doc, &path, &data.occurrences, false);
+        let links = link_names(scope, false);
+        let detached = fresh_name(name, &links);
+        let partner = fresh_name(
+            name,
+            &links.iter().copied().chain([detached.as_str()]).collect(),
+        );
+        actions.push(quick_fix(
+            format!(
+                "Detach this occurrence as `{}`, connected to the previous one by `{} = {}`",
+                detached, detached, partner
+            ),
+            uri,
+            diagnostic,
+            vec![
+                TextEdit {
+                    range,
+                    new_text: detached.clone(),
+                },
+                TextEdit {
+                    range: *previous,
+                    new_text: partner.clone(),
+                },
+                TextEdit {
+                    range: Range { start: end, end },
+                    new_text: format!(", {} = {}", detached, partner),
+                },
+            ],
+        ));
+    }
+
+    let scope = link_scope(doc, &path, &data.occurrences, true);
+    let hyperlink = if link_names(scope, true).contains(name.as_str()) {
+        fresh_name(name, &link_names(scope, true))
+    } else {
+        name.clone()
+    };
+    actions.push(quick_fix(
+        format!("Convert `{}` to hyperlink `!{}`", name, hyperlink),
+        uri,
+        diagnostic,
+        data.occurrences
+            .iter()
+            .map(|range| TextEdit {
+                range: *range,
+                new_text: format!("!{}", hyperlink),
+            })
+            .collect(),
+    ));
+
+    actions
+}
+
+#[test]
+fn test_multi_occur_fixes() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, info, _) = analyzed("a :- b(X), c(X), d(X), e(!X), g(X1), h(X1).");
+    let diagnostic = info
+        .diagnostics
+        .iter()
+        .find(|d| d.message == "Link occurs more than twice")
+        .unwrap();
+    let actions = quick_fixes(&uri, &doc, diagnostic);
+    assert_eq!(
+        actions
+            .iter()
+            .map(|action| action.title.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "Detach this occurrence as `X2`, connected to the previous one by `X2 = X3`",
+            "Convert `X` to hyperlink `!X1`"
+        ]
+    );
+    let mut edits = actions[0].edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri].clone();
+    edits.sort_by_key(|edit| edit.range.start);
+    assert_eq!(
+        edits
+            .into_iter()
+            .map(|edit| (edit.range.start.character, edit.new_text))
+            .collect::<Vec<_>>(),
+        vec![
+            (13, "X3".to_owned()),
+            (19, "X2".to_owned()),
+            (42, ", X2 = X3".to_owned())
+        ]
+    );
+    let edits = &actions[1].edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+    assert_eq!(edits.len(), 3);
+
+    // the connector is never added to the head
+    let (doc, info, _) = analyzed("a(X, X, X) :- .");
+    let diagnostic = info
+        .diagnostics
+        .iter()
+        .find(|d| d.message == "Link occurs more than twice")
+        .unwrap();
+    assert_eq!(quick_fixes(&uri, &doc, diagnostic).len(), 1);
+}
@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+
+use lmntalc::{frontend::ast::AtomName, ASTNode};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    document::Document,
+    syntax::{fresh_name, link_names, Functor},
+    utils::{span_to_range, to_position},
+};
+
+/// Atoms which can be written both nested and with links.
+fn nestable(doc: &Document, node: &ASTNode) -> bool {
+    doc.is_plain_atom(node)
+        || matches!(
+            node,
+            ASTNode::Atom {
+                name: (AtomName::Char(_), _),
+                ..
+            }
+        )
+}
+
+fn atom_text(doc: &Document, atom: &ASTNode, args: Vec<String>) -> String {
+    let ASTNode::Atom { name, .. } = atom else {
+        unreachable!()
+    };
+    let name = if doc.is_string(atom) {
+        doc.slice(name.1)
+    } else {
+        Functor::new(&name.0, 0).name
+    };
+    if args.is_empty() {
+        name
+    } else {
+        format!("{}({})", name, args.join(", "))
+    }
+}
+
+/// The process list containing the position and the process of it containing the position.
+fn process_at(doc: &Document, position: Position) -> Option<(Vec<&ASTNode>, usize)> {
+    let path = doc.path_at(position);
+    let index = path
+        .iter()
+        .rposition(|node| matches!(node, ASTNode::ProcessList { .. }))?;
+    matches!(path.get(index + 1), Some(ASTNode::Atom { .. })).then_some((path, index))
+}
+
+fn refactor(title: String, kind: CodeActionKind, uri: &Url, edits: Vec<TextEdit>) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Rewrite the atoms nested in the atom at the position as separate atoms connected by fresh
+/// links, e.g. `a(b(c))` into `a(X1), b(X2, X1), c(X2)`.
+///
+/// A nested atom has an implicit last argument connected to its parent, which becomes explicit.
+pub fn flatten(uri: &Url, doc: &Document, position: Position) -> Option<CodeAction> {
+    fn flatten_atom(
+        doc: &Document,
+        atom: &ASTNode,
+        parent: Option<String>,
+        taken: &mut HashSet<String>,
+        processes: &mut Vec<String>,
+    ) {
+        let ASTNode::Atom { args, .. } = atom else {
+            unreachable!()
+        };
+        let mut texts = vec![];
+        let mut nested = vec![];
+        for arg in args {
+            if nestable(doc, arg) {
+                let link = fresh_name("X", &taken.iter().map(String::as_str).collect());
+                taken.insert(link.clone());
+                texts.push(link.clone());
+                nested.push((arg, link));
+            } else {
+                texts.push(doc.slice(doc.extent(arg)));
+            }
+        }
+        texts.extend(parent);
+        processes.push(atom_text(doc, atom, texts));
+        for (arg, link) in nested {
+            flatten_atom(doc, arg, Some(link), taken, processes);
+        }
+    }
+
+    let (path, index) = process_at(doc, position)?;
+    let atom = path[index + 1];
+    let ASTNode::Atom { args, .. } = atom else {
+        unreachable!()
+    };
+    if !args.iter().any(|arg| nestable(doc, arg)) {
+        return None;
+    }
+
+    let scope = path[..index]
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Rule { .. }))
+        .unwrap_or(&path[0]);
+    let mut taken = link_names(scope, false)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    let mut processes = vec![];
+    flatten_atom(doc, atom, None, &mut taken, &mut processes);
+
+    Some(refactor(
+        "Flatten nested atoms into links".to_owned(),
+        CodeActionKind::REFACTOR_EXTRACT,
+        uri,
+        vec![TextEdit {
+            range: span_to_range(doc.extent(atom)),
+            new_text: processes.join(", "),
+        }],
+    ))
+}
+
+/// Nest the atoms connected to the arguments of the atom at the position by their last
+/// arguments, e.g. `a(X), b(X, Y), c(Y)` into `a(b(c))`.
+///
+/// Only the atoms of the same process list are nested.
+pub fn nest(uri: &Url, doc: &Document, position: Position) -> Option<CodeAction> {
+    fn nest_atom(
+        doc: &Document,
+        processes: &[ASTNode],
+        index: usize,
+        nested: bool,
+        used: &mut Vec<usize>,
+    ) -> String {
+        let ASTNode::Atom { args, .. } = &processes[index] else {
+            unreachable!()
+        };
+        let args = if nested {
+            &args[..args.len() - 1]
+        } else {
+            &args[..]
+        };
+        let mut texts = vec![];
+        for arg in args {
+            let partner = match arg {
+                ASTNode::Link {
+                    name: link,
+                    hyperlink: false,
+                    ..
+                } => processes.iter().enumerate().position(|(i, process)| {
+                    !used.contains(&i)
+                        && nestable(doc, process)
+                        && matches!(process, ASTNode::Atom { args, .. }
+                            if matches!(args.last(), Some(ASTNode::Link { name, hyperlink: false, .. }) if name == link))
+                }),
+                _ => None,
+            };
+            match partner {
+                Some(partner) => {
+                    used.push(partner);
+                    texts.push(nest_atom(doc, processes, partner, true, used));
+                }
+                None => texts.push(doc.slice(doc.extent(arg))),
+            }
+        }
+        atom_text(doc, &processes[index], texts)
+    }
+
+    let (path, index) = process_at(doc, position)?;
+    let ASTNode::ProcessList { processes, .. } = path[index] else {
+        unreachable!()
+    };
+    let atom = processes
+        .iter()
+        .position(|process| std::ptr::eq(process, path[index + 1]))?;
+    if !nestable(doc, &processes[atom]) {
+        return None;
+    }
+    let mut used = vec![atom];
+    let text = nest_atom(doc, processes, atom, false, &mut used);
+    if used.len() == 1 {
+        return None;
+    }
+
+    let extent = |i: usize| doc.extent(&processes[i]);
+    let mut edits = vec![TextEdit {
+        range: span_to_range(extent(atom)),
+        new_text: text,
+    }];
+    // remove the nested atoms along with the commas between them and their neighbors
+    for &i in &used[1..] {
+        let (start, end) = if i < atom {
+            (extent(i).low(), extent(i + 1).low())
+        } else {
+            (extent(i - 1).high(), extent(i).high())
+        };
+        edits.push(TextEdit {
+            range: Range {
+                start: to_position(start),
+                end: to_position(end),
+            },
+            new_text: String::new(),
+        });
+    }
+
+    Some(refactor(
+        "Nest linked atoms".to_owned(),
+        CodeActionKind::REFACTOR_INLINE,
+        uri,
+        edits,
+    ))
+}
+
+#[test]
+fn test_nesting() {
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let at = |character| Position { line: 0, character };
+    let new_texts = |action: Option<CodeAction>| {
+        let mut edits = action.unwrap().edit.unwrap().changes.unwrap()[&uri].clone();
+        edits.sort_by_key(|edit| edit.range.start);
+        edits
+            .into_iter()
+            .map(|edit| (edit.range.start.character, edit.new_text))
+            .collect::<Vec<_>>()
+    };
+
+    let (doc, _) = Document::parse("a(b(c), X1), d(X1).".to_owned());
+    assert_eq!(
+        new_texts(flatten(&uri, &doc, at(0))),
+        vec![(0, "a(X2, X1), b(X3, X2), c(X3)".to_owned())]
+    );
+    assert!(flatten(&uri, &doc, at(13)).is_none());
+
+    // string literals are kept as arguments
+    let (doc, _) = Document::parse("a(\"hello world\", b).".to_owned());
+    assert_eq!(
+        new_texts(flatten(&uri, &doc, at(0))),
+        vec![(0, "a(\"hello world\", X1), b(X1)".to_owned())]
+    );
+    let (doc, _) = Document::parse("a(b(\"x\")).".to_owned());
+    assert_eq!(
+        new_texts(flatten(&uri, &doc, at(0))),
+        vec![(0, "a(X1), b(\"x\", X1)".to_owned())]
+    );
+
+    let (doc, _) = Document::parse("b(Y, X), a(X, Z), c(Y), e(Z, W), f(W).".to_owned());
+    assert_eq!(
+        new_texts(nest(&uri, &doc, at(9))),
+        vec![
+            (0, String::new()),
+            (9, "a(b(c), Z)".to_owned()),
+            (16, String::new())
+        ]
+    );
+    assert!(nest(&uri, &doc, at(18)).is_none());
+}
@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+
+use lmntalc::{frontend::ast::AtomName, frontend::token::TokenKind, ASTNode};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    document::Document,
+    syntax::{fresh_name, visit_atoms, visit_nodes},
+    utils::to_position,
+};
+
+/// Create a rule consuming the atom at the position, if it occurs outside of rules and no rule
+/// has its functor in the head.
+///
+/// The rule is inserted after the last rule of the membrane containing the atom, or after the
+/// last statement if there are no rules.
+pub fn create_rule(uri: &Url, doc: &Document, position: Position) -> Option<CodeAction> {
+    let (atom, functor) = doc.atom_at(position)?;
+    let ASTNode::Atom { name, .. } = atom else {
+        unreachable!()
+    };
+    let base = match &name.0 {
+        AtomName::Plain(name) if doc.is_plain_atom(atom) => name.as_str(),
+        AtomName::Char(_) => "rule",
+        _ => return None,
+    };
+    let path = doc.path_at(position);
+    if path.iter().any(|node| matches!(node, ASTNode::Rule { .. })) {
+        return None;
+    }
+
+    let mut consumed = false;
+    let mut rule_names = HashSet::new();
+    visit_nodes(&doc.ast, &mut |node| {
+        if let ASTNode::Rule { name, head, .. } = node {
+            rule_names.insert(name.0.as_str());
+            visit_atoms(head, &mut |_, head_functor| {
+                consumed |= head_functor == functor
+            });
+        }
+    });
+    if consumed {
+        return None;
+    }
+    let rule_name = if rule_names.contains(base) {
+        fresh_name(base, &rule_names)
+    } else {
+        base.to_owned()
+    };
+
+    let membrane = path
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Membrane { .. }))?;
+    let ASTNode::Membrane {
+        process_lists,
+        rules,
+        ..
+    } = membrane
+    else {
+        unreachable!()
+    };
+    let last = rules
+        .iter()
+        .max_by_key(|rule| rule.span().low())
+        .or_else(|| process_lists.iter().max_by_key(|list| list.span().low()))?;
+    let extent = doc.extent(last);
+
+    // after the `.` ending the statement, which may be omitted at the end of a membrane
+    let next = doc
+        .tokens
+        .partition_point(|token| token.span.low() < extent.high());
+    let (end, separator) = match doc.tokens.get(next) {
+        Some(token) if token.kind == TokenKind::Dot => (token.span.high(), ""),
+        _ => (extent.high(), "."),
+    };
+    // after the comments following the statement on its line
+    let end = doc
+        .comments
+        .iter()
+        .filter(|comment| comment.low().line == end.line && comment.low() >= end)
+        .map(|comment| comment.high())
+        .max()
+        .unwrap_or(end);
+    let line_start = doc.offset_at(Position {
+        line: extent.low().line,
+        character: 0,
+    });
+    let indent = doc
+        .text()
+        .chars()
+        .skip(line_start)
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+
+    let links = (1..=functor.arity)
+        .map(|i| format!("X{}", i))
+        .collect::<Vec<_>>();
+    let head = if links.is_empty() {
+        functor.name.clone()
+    } else {
+        format!("{}({})", functor.name, links.join(", "))
+    };
+    let end = to_position(end);
+    Some(CodeAction {
+        title: format!("Create rule consuming {}", functor),
+        kind: Some(CodeActionKind::REFACTOR),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(
+                uri.clone(),
+                vec![TextEdit {
+                    range: Range { start: end, end },
+                    new_text: format!("{}\n{}{} @@ {} :- .", separator, indent, rule_name, head),
+                }],
+            )])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_create_rule() {
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _) = Document::parse(
+        "a(b, X), c(X).\nf @@ a(Y, Z) :- c(Y, Z). // f\n{\n  d(\"s\"), e.\n  e @@ x :- .\n}."
+            .to_owned(),
+    );
+    let create = |line, character| create_rule(&uri, &doc, Position { line, character });
+    let edit = |action: CodeAction| action.edit.unwrap().changes.unwrap()[&uri][0].clone();
+
+    let action = create(0, 2).unwrap();
+    assert_eq!(action.title, "Create rule consuming b/1");
+    let b = edit(action);
+    assert_eq!(b.range.start, Position::new(1, 29));
+    assert_eq!(b.new_text, "\nb @@ b(X1) :- .");
+
+    assert!(create(0, 0).is_none());
+    assert!(create(1, 16).is_none());
+
+    // string literals are not atoms to consume
+    assert!(create(3, 5).is_none());
+
+    let e = edit(create(3, 10).unwrap());
+    assert_eq!(e.range.start, Position::new(4, 13));
+    assert_eq!(e.new_text, "\n  e1 @@ e :- .");
+}
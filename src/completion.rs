@@ -0,0 +1,380 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use lmntalc::{frontend::token::TokenKind, ASTNode};
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, Documentation,
+    InsertTextFormat, MarkupContent, MarkupKind, Position,
+};
+
+use crate::{
+    analysis::rule::RuleLinks,
+    builtin::BUILTINS,
+    document::Document,
+    syntax::{visit_atoms, visit_nodes, Functor},
+    utils::{contains, span_to_range, to_position},
+};
+
+/// How close the atoms offered for completion are to the position, from the closest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Proximity {
+    Membrane,
+    Document,
+    Workspace,
+}
+
+/// The functors of the plain atoms in the document, which are the ones to complete.
+pub fn functors(doc: &Document) -> HashSet<Functor> {
+    let mut functors = HashSet::new();
+    visit_atoms(&doc.ast, &mut |atom, functor| {
+        if doc.is_plain_atom(atom) {
+            functors.insert(functor);
+        }
+    });
+    functors
+}
+
+pub fn completions(
+    doc: &Document,
+    rule_links: &[RuleLinks],
+    position: Position,
+    workspace: &HashSet<Functor>,
+) -> Vec<CompletionItem> {
+    if doc
+        .comments
+        .iter()
+        .any(|comment| contains(*comment, position))
+    {
+        return vec![];
+    }
+    let mut items = vec![];
+    match rule_part(doc, position) {
+        Some((_, RulePart::Guard)) => return guard_completions(),
+        Some((rule, RulePart::Body)) => {
+            items.extend(link_completions(rule, rule_links, position));
+            items.extend(context_completions(rule, position));
+        }
+        _ => {}
+    }
+    items.extend(atom_completions(doc, position, workspace));
+    items
+}
+
+/// The parts of a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RulePart {
+    Head,
+    Guard,
+    Body,
+}
+
+/// The innermost rule containing the position, along with the part of it the position is in.
+///
+/// The guard starts after the `:-` and ends at the `|`, after which the body starts.
+fn rule_part(doc: &Document, position: Position) -> Option<(&ASTNode, RulePart)> {
+    let rule = doc
+        .path_at(position)
+        .into_iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Rule { .. }))?;
+    let ASTNode::Rule { head, guard, .. } = rule else {
+        unreachable!()
+    };
+    // if the position is after the token following the node
+    let after = |node: &ASTNode, kind: TokenKind| {
+        let end = doc.extent(node).high();
+        let next = doc.tokens.partition_point(|token| token.span.low() < end);
+        doc.tokens
+            .get(next)
+            .is_some_and(|token| token.kind == kind && to_position(token.span.high()) <= position)
+    };
+    let part = match guard {
+        Some(guard) if after(guard, TokenKind::Vert) => RulePart::Body,
+        Some(_) if after(head, TokenKind::ColonDash) => RulePart::Guard,
+        None if after(head, TokenKind::ColonDash) => RulePart::Body,
+        _ => RulePart::Head,
+    };
+    Some((rule, part))
+}
+
+/// Complete a link in the body of a rule with the links of the head not connected to the body
+/// yet, and the links of the body occurring only once.
+///
+/// Links occurring twice are left out, since they cannot occur again.
+fn link_completions(
+    rule: &ASTNode,
+    rule_links: &[RuleLinks],
+    position: Position,
+) -> Vec<CompletionItem> {
+    let Some(links) = rule_links
+        .iter()
+        .find(|links| links.rule == span_to_range(rule.span()))
+    else {
+        return vec![];
+    };
+    links
+        .occurrences
+        .iter()
+        .filter_map(|(name, occurrences)| {
+            // the link being typed
+            let count = occurrences
+                .iter()
+                .filter(|range| !(range.start <= position && position <= range.end))
+                .count();
+            let head = links.head.contains(name);
+            (count == 1).then(|| CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(
+                    if head {
+                        "Link from the head"
+                    } else {
+                        "Link occurring once in the body"
+                    }
+                    .to_owned(),
+                ),
+                sort_text: Some(format!("{}{}", if head { 0 } else { 1 }, name)),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Complete the process contexts bound in the head of the rule.
+///
+/// Rule contexts such as `@r` are not supported by the parser yet.
+fn context_completions(rule: &ASTNode, position: Position) -> Vec<CompletionItem> {
+    let ASTNode::Rule {
+        head, propagation, ..
+    } = rule
+    else {
+        unreachable!()
+    };
+    let mut names = BTreeSet::new();
+    for part in [Some(head), propagation.as_ref()].into_iter().flatten() {
+        visit_nodes(part, &mut |node| {
+            if let ASTNode::Context { name, span } = node {
+                if !contains(*span, position) {
+                    names.insert(format!("${}", name));
+                }
+            }
+        });
+    }
+    names
+        .into_iter()
+        .map(|name| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some("Process context from the head".to_owned()),
+            sort_text: Some(format!("0{}", name)),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Complete the type checks, comparisons and arithmetic operators of guards.
+fn guard_completions() -> Vec<CompletionItem> {
+    BUILTINS
+        .iter()
+        // hyperlinks can only be fused in the body
+        .filter(|builtin| builtin.name != "><")
+        .map(|builtin| {
+            let function = builtin.signature.starts_with(&format!("{}(", builtin.name));
+            let insert_text = if function {
+                let placeholders = builtin
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        format!("${{{}:{}}}", i + 1, arg.trim_start_matches(['+', '-']))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{}({})$0", builtin.name, placeholders.join(", "))
+            } else {
+                builtin.name.to_owned()
+            };
+            CompletionItem {
+                label: builtin.name.to_owned(),
+                kind: Some(if function {
+                    CompletionItemKind::FUNCTION
+                } else {
+                    CompletionItemKind::OPERATOR
+                }),
+                detail: Some(builtin.signature.to_owned()),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: builtin.markdown(),
+                })),
+                insert_text: Some(insert_text),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Complete the atom at the position with the atoms of its membrane, of the document and of the
+/// other documents in the workspace, in this order.
+fn atom_completions(
+    doc: &Document,
+    position: Position,
+    workspace: &HashSet<Functor>,
+) -> Vec<CompletionItem> {
+    let path = doc.path_at(position);
+    // a nested atom is written without its last argument
+    let nested = match path.as_slice() {
+        [.., parent, ASTNode::Atom { name, .. }] if contains(name.1, position) => {
+            matches!(parent, ASTNode::Atom { .. })
+        }
+        [.., ASTNode::Atom { .. }] => true,
+        [.., ASTNode::Link { .. } | ASTNode::Context { .. }] => return vec![],
+        _ => false,
+    };
+
+    let mut proximities = HashMap::new();
+    let mut add = |node: &ASTNode, proximity: Proximity| {
+        visit_atoms(node, &mut |atom, functor| {
+            let ASTNode::Atom { name, .. } = atom else {
+                return;
+            };
+            // the atom being typed
+            if !doc.is_plain_atom(atom) || contains(name.1, position) {
+                return;
+            }
+            proximities.entry(functor).or_insert(proximity);
+        })
+    };
+    if let Some(membrane) = path
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Membrane { .. }))
+    {
+        add(membrane, Proximity::Membrane);
+    }
+    add(&doc.ast, Proximity::Document);
+    for functor in workspace {
+        proximities
+            .entry(functor.clone())
+            .or_insert(Proximity::Workspace);
+    }
+
+    proximities
+        .into_iter()
+        .filter(|(functor, _)| !nested || functor.arity > 0)
+        .map(|(functor, proximity)| completion_item(&functor, proximity, nested))
+        .collect()
+}
+
+fn completion_item(functor: &Functor, proximity: Proximity, nested: bool) -> CompletionItem {
+    let args = functor.arity - nested as usize;
+    let insert_text = if args == 0 {
+        functor.name.clone()
+    } else {
+        let placeholders = (1..=args)
+            .map(|i| format!("${{{}:X{}}}", i, i))
+            .collect::<Vec<_>>();
+        format!("{}({})$0", functor.name, placeholders.join(", "))
+    };
+    CompletionItem {
+        label: functor.name.clone(),
+        label_details: Some(CompletionItemLabelDetails {
+            detail: Some(format!("/{}", functor.arity)),
+            description: None,
+        }),
+        kind: Some(CompletionItemKind::CONSTRUCTOR),
+        detail: Some(functor.to_string()),
+        sort_text: Some(format!("{}{}", proximity as u8, functor)),
+        filter_text: Some(functor.name.clone()),
+        insert_text: Some(insert_text),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_atom_completions() {
+    let (doc, _) = Document::parse("a(X), {b(X), c}. f(d(e)).".to_owned());
+    let workspace = HashSet::from([Functor {
+        name: "w".to_owned(),
+        arity: 3,
+    }]);
+    let at = |character| Position { line: 0, character };
+    let items = |character| {
+        let mut items = completions(&doc, &[], at(character), &workspace)
+            .into_iter()
+            .map(|item| (item.sort_text.unwrap(), item.insert_text.unwrap()))
+            .collect::<Vec<_>>();
+        items.sort();
+        items
+    };
+
+    assert_eq!(
+        items(13),
+        vec![
+            ("0b/1".to_owned(), "b(${1:X1})$0".to_owned()),
+            ("1a/1".to_owned(), "a(${1:X1})$0".to_owned()),
+            ("1d/2".to_owned(), "d(${1:X1}, ${2:X2})$0".to_owned()),
+            ("1e/1".to_owned(), "e(${1:X1})$0".to_owned()),
+            ("1f/1".to_owned(), "f(${1:X1})$0".to_owned()),
+            (
+                "2w/3".to_owned(),
+                "w(${1:X1}, ${2:X2}, ${3:X3})$0".to_owned()
+            ),
+        ]
+    );
+
+    // the atom at the position is nested in `d`, so the atoms are written with one argument less
+    let nested = items(21);
+    assert!(nested.contains(&("0d/2".to_owned(), "d(${1:X1})$0".to_owned())));
+    assert!(!nested
+        .iter()
+        .any(|(sort_text, _)| sort_text.ends_with("c/0")));
+
+    assert!(items(2).is_empty());
+}
+
+#[test]
+fn test_link_completions() {
+    use crate::document::analyzed;
+
+    let (doc, info, _) = analyzed("a(X, Y, Z), b(W, W) :- c(Y, V), d(Y, T), e(T, U).");
+    let at = |character| Position { line: 0, character };
+    let links = |character| {
+        let mut links = completions(&doc, &info.rule_links, at(character), &HashSet::new())
+            .into_iter()
+            .filter(|item| item.kind == Some(CompletionItemKind::VARIABLE))
+            .map(|item| item.sort_text.unwrap())
+            .collect::<Vec<_>>();
+        links.sort();
+        links
+    };
+
+    // `Y` is used twice and `T` is connected, while `W` is connected in the head
+    assert_eq!(links(45), vec!["0X", "0Z", "1U", "1V"]);
+    // the occurrence of `U` being typed is not counted
+    assert_eq!(links(47), vec!["0X", "0Z", "1V"]);
+    assert!(links(2).is_empty());
+}
+
+#[test]
+fn test_rule_completions() {
+    let (doc, _) = Document::parse("a($p, X) :- int(X) | b($p, X), $q.".to_owned());
+    let at = |character| Position { line: 0, character };
+    let labels = |character| {
+        completions(&doc, &[], at(character), &HashSet::new())
+            .into_iter()
+            .map(|item| item.label)
+            .collect::<Vec<_>>()
+    };
+
+    let guard = completions(&doc, &[], at(14), &HashSet::new());
+    let int = guard.iter().find(|item| item.label == "int").unwrap();
+    assert_eq!(int.insert_text.as_deref(), Some("int(${1:X})$0"));
+    assert!(guard.iter().any(|item| item.label == "=:="));
+    assert!(!guard
+        .iter()
+        .any(|item| item.label == "><" || item.label == "a"));
+
+    let body = labels(33);
+    assert!(body.contains(&"$p".to_owned()) && !body.contains(&"$q".to_owned()));
+    assert!(!labels(3).contains(&"$p".to_owned()));
+}
@@ -8,4 +8,7 @@ pub struct Config {
     pub slim_args: Vec<String>,
     pub compiler_path: String,
     pub compiler_args: Vec<String>,
+    /// Show the arity of nested atoms as inlay hints.
+    #[serde(default)]
+    pub nested_arity_hints: bool,
 }
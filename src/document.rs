@@ -0,0 +1,329 @@
+use lmntalc::{
+    frontend::{ast::AtomName, parsing::ParseWarningType, token::TokenKind},
+    util::{Pos, Source, Span},
+    ASTNode, Token,
+};
+use tower_lsp::lsp_types::Position;
+
+use crate::{
+    diagnostics::Diagnostics,
+    syntax::{children, Functor},
+    utils::{contains, to_position},
+};
+
+/// A parsed LMNtal source file.
+#[derive(Debug)]
+pub struct Document {
+    pub src: Source,
+    pub tokens: Vec<Token>,
+    pub ast: ASTNode,
+    /// The comments, which are dropped by the lexer.
+    pub comments: Vec<Span>,
+    /// The tokens before which a comma between processes is missing.
+    pub missing_commas: Vec<Span>,
+    /// Whether the source has lexing or parsing errors, in which case the AST may be partial.
+    pub has_errors: bool,
+    /// Character offset of the beginning of every line.
+    line_offsets: Vec<usize>,
+}
+
+impl Document {
+    /// Lex and parse the text, returning the document along with the syntax diagnostics.
+    pub fn parse(text: String) -> (Self, Diagnostics) {
+        let src = Source::from_string(text);
+        let mut lexer = lmntalc::LMNtalLexer::new(&src);
+        let mut parser = lmntalc::LMNtalParser::new();
+
+        let mut diagnostics = Diagnostics::default();
+        let lexing_result = lexer.lex();
+        let mut has_errors = !lexing_result.errors.is_empty();
+        let mut missing_commas = vec![];
+        diagnostics.extend(lexing_result.errors);
+
+        let tokens = lexing_result.tokens;
+        let ast = if tokens.is_empty() {
+            // the parser expects at least one token
+            ASTNode::Membrane {
+                name: ("_init".to_owned(), Span::dummy()),
+                process_lists: vec![],
+                rules: vec![],
+                span: Span::dummy(),
+            }
+        } else {
+            let parsing_result = parser.parse(tokens.clone());
+            has_errors |= !parsing_result.parsing_errors.is_empty();
+            diagnostics.extend(parsing_result.parsing_errors);
+            missing_commas.extend(
+                parsing_result
+                    .parsing_warnings
+                    .iter()
+                    .filter(|warning| {
+                        matches!(warning.ty, ParseWarningType::MissingCommaBetweenProcesses)
+                    })
+                    .map(|warning| warning.span),
+            );
+            diagnostics.extend(parsing_result.parsing_warnings);
+            parsing_result.ast
+        };
+
+        let mut line_offsets = vec![0];
+        for (i, c) in src.source().chars().enumerate() {
+            if c == '\n' {
+                line_offsets.push(i + 1);
+            }
+        }
+
+        let mut document = Self {
+            src,
+            tokens,
+            ast,
+            comments: vec![],
+            missing_commas,
+            has_errors,
+            line_offsets,
+        };
+        document.comments = scan_comments(document.text())
+            .into_iter()
+            .map(|(low, high)| document.span_at(low, high))
+            .collect();
+        (document, diagnostics)
+    }
+
+    pub fn text(&self) -> &str {
+        self.src.source()
+    }
+
+    /// Convert a position to a character offset.
+    pub fn offset_at(&self, position: Position) -> usize {
+        let line = (position.line as usize).min(self.line_offsets.len() - 1);
+        self.line_offsets[line] + position.character as usize
+    }
+
+    /// Convert a character offset to a position.
+    pub fn position_at(&self, offset: usize) -> Position {
+        let line = match self.line_offsets.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        Position {
+            line: line as u32,
+            character: (offset - self.line_offsets[line]) as u32,
+        }
+    }
+
+    /// The indices of the opening and closing tokens of every list literal, in the order they
+    /// are closed.
+    ///
+    /// The parser does not support list literals, so they are matched on the tokens.
+    pub fn lists(&self) -> Vec<(usize, usize)> {
+        let mut open = vec![];
+        let mut lists = vec![];
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token.kind {
+                TokenKind::LeftBracket => open.push(i),
+                TokenKind::RightBracket => lists.extend(open.pop().map(|start| (start, i))),
+                _ => {}
+            }
+        }
+        lists
+    }
+
+    /// Where to insert the comma missing before the token at the position, which is right
+    /// after the previous token.
+    pub fn comma_position(&self, position: Position) -> Option<Position> {
+        let index = self
+            .tokens
+            .iter()
+            .position(|token| to_position(token.span.low()) == position)?;
+        Some(to_position(self.tokens[index.checked_sub(1)?].span.high()))
+    }
+
+    /// The span between two character offsets.
+    pub fn span_at(&self, low: usize, high: usize) -> Span {
+        let pos = |offset| {
+            let position = self.position_at(offset);
+            Pos::new(offset as u32, position.line, position.character)
+        };
+        Span::new(pos(low), pos(high))
+    }
+
+    /// The text covered by the span.
+    pub fn slice(&self, span: Span) -> String {
+        let low = span.low().offset as usize;
+        let high = span.high().offset as usize;
+        self.text().chars().skip(low).take(high - low).collect()
+    }
+
+    /// Check if the node is a string literal, which the parser reads as a plain atom.
+    pub fn is_string(&self, node: &ASTNode) -> bool {
+        matches!(
+            node,
+            ASTNode::Atom {
+                name: (AtomName::Plain(_), span),
+                ..
+            } if self.slice(*span).starts_with('"')
+        )
+    }
+
+    /// Check if the node is a plain atom other than a string literal.
+    ///
+    /// Keyword, operator and literal atoms are built in, so only plain atoms are renamed,
+    /// completed or given rules.
+    pub fn is_plain_atom(&self, node: &ASTNode) -> bool {
+        matches!(
+            node,
+            ASTNode::Atom {
+                name: (AtomName::Plain(_), _),
+                ..
+            }
+        ) && !self.is_string(node)
+    }
+
+    /// The span of the whole source text of a node.
+    ///
+    /// The spans given by the parser only cover the operator of an operator atom, and start at
+    /// the beginning of the file for anonymous membranes. The span of a character atom also
+    /// misses its opening quote.
+    pub fn extent(&self, node: &ASTNode) -> Span {
+        match node {
+            ASTNode::Atom { name, args, span } => {
+                let mut span = *span;
+                if let AtomName::Char(_) = name.0 {
+                    let low = span.low().offset as usize - 1;
+                    span = span.merge(self.span_at(low, low));
+                }
+                args.iter()
+                    .fold(span, |span, arg| span.merge(self.extent(arg)))
+            }
+            ASTNode::Membrane { name, span, .. } if name.0.is_empty() => {
+                let close = self.tokens.partition_point(|t| t.span.high() < span.high());
+                let mut depth = 0;
+                for token in self.tokens[..=close.min(self.tokens.len() - 1)]
+                    .iter()
+                    .rev()
+                {
+                    match token.kind {
+                        TokenKind::RightBrace => depth += 1,
+                        TokenKind::LeftBrace => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Span::new(token.span.low(), span.high());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                *span
+            }
+            ASTNode::Rule { name, span, .. } if !name.1.is_empty() => span.merge(name.1),
+            _ => node.span(),
+        }
+    }
+
+    /// The nodes containing the position, from the root to the innermost one.
+    pub fn path_at(&self, position: Position) -> Vec<&ASTNode> {
+        let mut path = vec![&self.ast];
+        while let Some(child) = children(path[path.len() - 1])
+            .into_iter()
+            .find(|child| contains(self.extent(child), position))
+        {
+            path.push(child);
+        }
+        path
+    }
+
+    /// The atom whose name is at the position, along with its functor.
+    pub fn atom_at(&self, position: Position) -> Option<(&ASTNode, Functor)> {
+        let path = self.path_at(position);
+        let nested = path.len() >= 2 && matches!(path[path.len() - 2], ASTNode::Atom { .. });
+        match path.last()? {
+            atom @ ASTNode::Atom { name, args, .. } if contains(name.1, position) => {
+                Some((atom, Functor::new(&name.0, args.len() + nested as usize)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The functor of the atom the link at the position is an argument of, along with the
+    /// one-based index of the argument.
+    pub fn link_argument(&self, position: Position) -> Option<(Functor, usize)> {
+        let path = self.path_at(position);
+        let [.., grandparent, ASTNode::Atom { name, args, .. }, link @ ASTNode::Link { .. }] =
+            path.as_slice()
+        else {
+            return None;
+        };
+        let index = args.iter().position(|arg| std::ptr::eq(arg, *link))?;
+        let nested = matches!(grandparent, ASTNode::Atom { .. });
+        Some((
+            Functor::new(&name.0, args.len() + nested as usize),
+            index + 1,
+        ))
+    }
+}
+
+/// The character offsets of the comments in the text, skipping over character and string
+/// literals the same way as the lexer does.
+fn scan_comments(text: &str) -> Vec<(usize, usize)> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut comments = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('\'', _) => i += 3,
+            ('"', _) => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            ('/', Some('/')) => {
+                let start = i;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                comments.push((start, i));
+            }
+            ('/', Some('*')) => {
+                let start = i;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                comments.push((start, i));
+            }
+            _ => i += 1,
+        }
+    }
+    comments
+}
+
+/// Parse and analyze the text as `file:///test.lmn`, along with the reference map of it.
+#[cfg(test)]
+pub fn analyzed(
+    text: &str,
+) -> (
+    Document,
+    crate::analysis::ProgramInfo,
+    crate::reference::RefereceMap,
+) {
+    let (doc, _) = Document::parse(text.to_owned());
+    let uri = tower_lsp::lsp_types::Url::parse("file:///test.lmn").unwrap();
+    let info = crate::analysis::Analyzer::new(uri, &doc.ast).analyze();
+    let ref_map = crate::reference::RefereceMap::new(
+        info.refs.clone(),
+        info.symbols.clone(),
+        info.guard_checks.clone(),
+    );
+    (doc, info, ref_map)
+}
+
+#[test]
+fn test_parse_empty() {
+    let (doc, diagnostics) = Document::parse(String::new());
+    assert!(diagnostics.diagnostics.is_empty());
+    assert!(!doc.has_errors);
+    assert!(doc.path_at(Position::new(0, 0)).len() <= 1);
+}
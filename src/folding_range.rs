@@ -0,0 +1,88 @@
+use lmntalc::{util::Span, ASTNode};
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind, Position};
+
+use crate::{document::Document, syntax::visit_nodes};
+
+/// Folding ranges for the membranes, rules, block comments and list literals spanning several
+/// lines.
+pub fn folding_ranges(doc: &Document) -> Vec<FoldingRange> {
+    let mut ranges = vec![];
+    visit_nodes(&doc.ast, &mut |node| match node {
+        // the root membrane is the whole document
+        ASTNode::Membrane { .. } if std::ptr::eq(node, &doc.ast) => {}
+        ASTNode::Membrane { .. } | ASTNode::Rule { .. } => {
+            ranges.extend(folding_range(doc, doc.extent(node), None));
+        }
+        _ => {}
+    });
+
+    for comment in &doc.comments {
+        if doc.slice(*comment).starts_with("/*") {
+            ranges.extend(folding_range(
+                doc,
+                *comment,
+                Some(FoldingRangeKind::Comment),
+            ));
+        }
+    }
+
+    for (start, end) in doc.lists() {
+        let span = doc.tokens[start].span.merge(doc.tokens[end].span);
+        ranges.extend(folding_range(doc, span, None));
+    }
+
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+/// The folding range of a span covering more than one line.
+///
+/// A closing bracket at the beginning of the last line is left unfolded.
+fn folding_range(
+    doc: &Document,
+    span: Span,
+    kind: Option<FoldingRangeKind>,
+) -> Option<FoldingRange> {
+    let (start_line, mut end_line) = (span.low().line, span.high().line);
+    let line_start = doc.offset_at(Position {
+        line: end_line,
+        character: 0,
+    });
+    let last_line = doc
+        .text()
+        .chars()
+        .skip(line_start)
+        .take(span.high().offset as usize - line_start)
+        .collect::<String>();
+    if matches!(last_line.trim_start(), "}" | "]") {
+        end_line -= 1;
+    }
+    (start_line < end_line).then_some(FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind,
+        collapsed_text: None,
+    })
+}
+
+#[test]
+fn test_folding_ranges() {
+    let (doc, _) = Document::parse(
+        "{\n  a, {b}.\n  r @@\n    a :- c.\n}.\n/* long\n comment */\nd([1,\n  2]). {e\n}."
+            .to_owned(),
+    );
+    assert_eq!(
+        folding_ranges(&doc)
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            (0, 3, None),
+            (2, 3, None),
+            (5, 6, Some(FoldingRangeKind::Comment)),
+            (7, 8, None),
+        ]
+    );
+}
@@ -0,0 +1,605 @@
+use lmntalc::{
+    frontend::{
+        ast::AtomName,
+        token::{Operator, TokenKind},
+    },
+    util::{Pos, Span},
+    ASTNode,
+};
+use tower_lsp::lsp_types::{FormattingOptions, Position, Range, TextEdit};
+
+use crate::{
+    document::Document,
+    syntax::{children, visit_nodes},
+    utils::to_position,
+};
+
+/// Rules and process lists longer than this are broken into several lines.
+const MAX_WIDTH: usize = 80;
+
+/// Format the whole document.
+///
+/// Returns `None` if the document has syntax errors, as the AST may miss part of the source.
+pub fn format(doc: &Document, options: &FormattingOptions) -> Option<Vec<TextEdit>> {
+    if doc.has_errors {
+        return None;
+    }
+    let text = Formatter::new(doc, options).format();
+    if text == doc.text() {
+        return Some(vec![]);
+    }
+    Some(vec![TextEdit {
+        range: Range {
+            start: doc.position_at(0),
+            end: doc.position_at(doc.text().chars().count()),
+        },
+        new_text: text,
+    }])
+}
+
+/// Format the statements overlapping the range, in the innermost membrane containing it.
+pub fn format_range(
+    doc: &Document,
+    range: Range,
+    options: &FormattingOptions,
+) -> Option<Vec<TextEdit>> {
+    if doc.has_errors {
+        return None;
+    }
+    let formatter = Formatter::new(doc, options);
+    let (low, high) = (doc.offset_at(range.start), doc.offset_at(range.end));
+    Some(
+        formatter
+            .statements_in(&doc.ast, 0, low, high)
+            .into_iter()
+            .map(|(statement, level)| formatter.statement_edit(statement, level))
+            .filter(|edit| {
+                let low = doc.offset_at(edit.range.start);
+                let high = doc.offset_at(edit.range.end);
+                doc.slice(doc.span_at(low, high)) != edit.new_text
+            })
+            .collect(),
+    )
+}
+
+/// Format the statement just terminated by the `.` before the position.
+pub fn format_on_type(
+    doc: &Document,
+    position: Position,
+    options: &FormattingOptions,
+) -> Option<Vec<TextEdit>> {
+    let offset = doc.offset_at(position);
+    let dot = doc.tokens.iter().position(|token| {
+        token.kind == TokenKind::Dot && token.span.high().offset as usize == offset
+    })?;
+    // the end of the statement, which may be followed by spaces before the `.`
+    let end = to_position(doc.tokens[dot.checked_sub(1)?].span.high());
+    format_range(doc, Range { start: end, end }, options)
+}
+
+/// A pretty-printer over the AST of a document.
+///
+/// Comments between statements are kept in place, and statements with comments inside are
+/// kept as they are written, so formatting twice gives the same result.
+pub struct Formatter<'a> {
+    doc: &'a Document,
+    indent: String,
+    tab_size: usize,
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(doc: &'a Document, options: &FormattingOptions) -> Self {
+        let tab_size = options.tab_size.max(1) as usize;
+        Self {
+            doc,
+            indent: if options.insert_spaces {
+                " ".repeat(tab_size)
+            } else {
+                "\t".to_owned()
+            },
+            tab_size,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        let mut text = self.statements(&self.doc.ast, 0);
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    fn indent(&self, level: usize) -> String {
+        self.indent.repeat(level)
+    }
+
+    /// Check if a single line text fits in the width after the indentation and `used` columns.
+    fn fits(&self, level: usize, used: usize, text: &str) -> bool {
+        !text.contains('\n') && level * self.tab_size + used + text.chars().count() < MAX_WIDTH
+    }
+
+    fn comments_in(&self, span: Span) -> impl Iterator<Item = &Span> {
+        self.doc
+            .comments
+            .iter()
+            .filter(move |comment| span.contains(**comment))
+    }
+
+    /// Check if there are comments inside the node but outside of its nested membranes,
+    /// which cannot be placed anywhere else once the node is rearranged.
+    fn has_own_comments(&self, node: &ASTNode) -> bool {
+        let mut membranes = vec![];
+        for child in children(node) {
+            visit_nodes(child, &mut |node| {
+                if let ASTNode::Membrane { .. } = node {
+                    membranes.push(self.doc.extent(node));
+                }
+            });
+        }
+        self.comments_in(self.doc.extent(node))
+            .any(|comment| !membranes.iter().any(|m| m.contains(*comment)))
+    }
+
+    /// The statements of a membrane, one per line, along with the comments between them.
+    fn statements(&self, membrane: &ASTNode, level: usize) -> String {
+        let statements = children(membrane);
+        let extents = statements
+            .iter()
+            .map(|statement| self.doc.extent(statement))
+            .collect::<Vec<_>>();
+
+        // statements and comments in source order, with their first and last lines
+        let mut items = statements
+            .iter()
+            .zip(&extents)
+            .map(|(statement, extent)| {
+                let text = format!("{}.", self.statement(statement, level));
+                (extent.low(), self.statement_end(*extent).line, text)
+            })
+            .collect::<Vec<_>>();
+        // the extent of the root membrane ends at its last statement, before trailing comments
+        let root = std::ptr::eq(membrane, &self.doc.ast);
+        let extent = self.doc.extent(membrane);
+        items.extend(
+            self.doc
+                .comments
+                .iter()
+                .filter(|comment| root || extent.contains(**comment))
+                .filter(|comment| !extents.iter().any(|extent| extent.contains(**comment)))
+                .map(|comment| (comment.low(), comment.high().line, self.doc.slice(*comment))),
+        );
+        items.sort_by_key(|item| item.0);
+
+        let mut text = String::new();
+        let mut last_line = None;
+        for (start, end_line, item) in items {
+            match last_line {
+                // comments after a statement on the same line stay there
+                Some(line) if line == start.line && item.starts_with('/') => text.push(' '),
+                Some(line) => {
+                    text.push('\n');
+                    if start.line > line + 1 {
+                        text.push('\n');
+                    }
+                    text.push_str(&self.indent(level));
+                }
+                None => text.push_str(&self.indent(level)),
+            }
+            text.push_str(&item);
+            last_line = Some(end_line);
+        }
+        text
+    }
+
+    /// The statements of the membrane overlapping the range between the offsets, along with
+    /// their indentation levels.
+    ///
+    /// If the range is inside a single statement, the statements of the innermost membrane in
+    /// it containing the range are taken instead.
+    fn statements_in<'b>(
+        &self,
+        membrane: &'b ASTNode,
+        level: usize,
+        low: usize,
+        high: usize,
+    ) -> Vec<(&'b ASTNode, usize)> {
+        let offsets = |node| {
+            let extent = self.doc.extent(node);
+            (extent.low().offset as usize, extent.high().offset as usize)
+        };
+        let statements = children(membrane)
+            .into_iter()
+            .filter(|statement| {
+                let (start, end) = offsets(statement);
+                if low == high {
+                    start <= low && low <= end
+                } else {
+                    start < high && low < end
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let [statement] = statements.as_slice() {
+            let mut inner = None;
+            let mut depth = 0;
+            visit_nodes(statement, &mut |node| {
+                if let ASTNode::Membrane { .. } = node {
+                    let (start, end) = offsets(node);
+                    if start <= low && high <= end && (start < low || high < end) {
+                        inner = Some(node);
+                        depth += 1;
+                    }
+                }
+            });
+            if let Some(inner) = inner {
+                let nested = self.statements_in(inner, level + depth, low, high);
+                if !nested.is_empty() {
+                    return nested;
+                }
+            }
+        }
+        statements
+            .into_iter()
+            .map(|statement| (statement, level))
+            .collect()
+    }
+
+    /// Replace a statement and the `.` after it with its formatted text.
+    fn statement_edit(&self, statement: &ASTNode, level: usize) -> TextEdit {
+        let extent = self.doc.extent(statement);
+        let mut end = self.statement_end(extent);
+        let mut new_text = self.statement(statement, level);
+        if end != extent.high() {
+            if self
+                .doc
+                .comments
+                .iter()
+                .any(|comment| extent.high() <= comment.low() && comment.high() <= end)
+            {
+                end = extent.high();
+            } else {
+                new_text.push('.');
+            }
+        }
+        TextEdit {
+            range: Range {
+                start: to_position(extent.low()),
+                end: to_position(end),
+            },
+            new_text,
+        }
+    }
+
+    /// The end of a statement, including the `.` after it.
+    fn statement_end(&self, extent: Span) -> Pos {
+        let next = self
+            .doc
+            .tokens
+            .partition_point(|token| token.span.low() < extent.high());
+        match self.doc.tokens.get(next) {
+            Some(token) if token.kind == TokenKind::Dot => token.span.high(),
+            _ => extent.high(),
+        }
+    }
+
+    fn statement(&self, statement: &ASTNode, level: usize) -> String {
+        if self.has_own_comments(statement) {
+            return self.doc.slice(self.doc.extent(statement));
+        }
+        match statement {
+            ASTNode::Rule { .. } => self.rule(statement, level),
+            _ => self.list(statement, level, 0),
+        }
+    }
+
+    fn rule(&self, rule: &ASTNode, level: usize) -> String {
+        let ASTNode::Rule {
+            name,
+            head,
+            propagation,
+            guard,
+            body,
+            ..
+        } = rule
+        else {
+            unreachable!()
+        };
+        let prefix = if name.1.is_empty() {
+            String::new()
+        } else {
+            format!("{} @@ ", name.0)
+        };
+
+        let flat = || -> Option<String> {
+            let mut text = prefix.clone();
+            if let Some(propagation) = propagation {
+                text += &format!("{} \\ ", self.flat(propagation)?);
+            }
+            text += &format!("{} :- ", self.flat(head)?);
+            if let Some(guard) = guard {
+                text += &format!("{} | ", self.flat(guard)?);
+            }
+            if let Some(body) = body {
+                text += &self.flat(body)?;
+            }
+            Some(text)
+        };
+        if let Some(text) = flat() {
+            if self.fits(level, 1, &text) {
+                return text;
+            }
+        }
+
+        // head :-
+        //     guard |
+        //     body
+        let lhs = |used: usize| {
+            let mut text = String::new();
+            if let Some(propagation) = propagation {
+                text += &self.list(propagation, level, used);
+                text += " \\ ";
+            }
+            let used = match text.rsplit_once('\n') {
+                Some((_, last)) => last.chars().count(),
+                None => used + text.chars().count(),
+            };
+            text + &self.list(head, level, used + 3)
+        };
+        let mut text = lhs(prefix.chars().count());
+        if text.contains('\n') && !prefix.is_empty() {
+            // give the head a line of its own rather than wrapping it after the name
+            text = format!("{}\n{}{}", prefix.trim_end(), self.indent(level), lhs(0));
+        } else {
+            text = prefix + &text;
+        }
+        text += " :-";
+        if let Some(guard) = guard {
+            text += &format!(
+                "\n{}{} |",
+                self.indent(level + 1),
+                self.list(guard, level + 1, 2)
+            );
+        }
+        match body {
+            Some(body) => {
+                text += &format!(
+                    "\n{}{}",
+                    self.indent(level + 1),
+                    self.list(body, level + 1, 0)
+                )
+            }
+            None => text.push(' '),
+        }
+        text
+    }
+
+    /// A process list on a single line if it fits, or one process per line otherwise.
+    fn list(&self, list: &ASTNode, level: usize, used: usize) -> String {
+        let processes = children(list)
+            .into_iter()
+            .map(|process| self.process(process, level))
+            .collect::<Vec<_>>();
+        let line = processes.join(", ");
+        if self.fits(level, used + 1, &line) {
+            line
+        } else {
+            processes.join(&format!(",\n{}", self.indent(level)))
+        }
+    }
+
+    fn process(&self, process: &ASTNode, level: usize) -> String {
+        if let Some(text) = self.flat(process) {
+            if !matches!(process, ASTNode::Membrane { .. }) || self.fits(level, 0, &text) {
+                return text;
+            }
+        }
+        match process {
+            ASTNode::Atom { name, args, .. } => format!(
+                "{}({})",
+                self.atom_name(name),
+                args.iter()
+                    .map(|arg| self.process(arg, level))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ASTNode::Membrane { name, .. } => {
+                let statements = self.statements(process, level + 1);
+                if statements.is_empty() {
+                    format!("{}{{}}", name.0)
+                } else {
+                    format!("{}{{\n{}\n{}}}", name.0, statements, self.indent(level))
+                }
+            }
+            _ => unreachable!("links and contexts are always flat"),
+        }
+    }
+
+    /// The node on a single line, if it can be written so.
+    ///
+    /// Membranes with rules, several statements or comments are written as blocks.
+    fn flat(&self, node: &ASTNode) -> Option<String> {
+        match node {
+            ASTNode::Atom { name, args, .. } => {
+                if self.comments_in(self.doc.extent(node)).next().is_some() {
+                    return None;
+                }
+                match (&name.0, args.as_slice()) {
+                    (AtomName::Operator(op), [operand]) => {
+                        Some(format!("{}{}", op, self.operand(operand, |_| true)?))
+                    }
+                    (AtomName::Operator(op), [left, right]) => {
+                        let prec = precedence(op);
+                        Some(format!(
+                            "{} {} {}",
+                            self.operand(left, |p| p < prec)?,
+                            op,
+                            self.operand(right, |p| p <= prec)?
+                        ))
+                    }
+                    (_, []) => Some(self.atom_name(name)),
+                    (_, args) => Some(format!(
+                        "{}({})",
+                        self.atom_name(name),
+                        args.iter()
+                            .map(|arg| self.flat(arg))
+                            .collect::<Option<Vec<_>>>()?
+                            .join(", ")
+                    )),
+                }
+            }
+            ASTNode::Link {
+                name, hyperlink, ..
+            } => Some(format!("{}{}", if *hyperlink { "!" } else { "" }, name)),
+            ASTNode::Context { name, .. } => Some(format!("${}", name)),
+            ASTNode::Membrane {
+                name,
+                process_lists,
+                rules,
+                ..
+            } => {
+                if !rules.is_empty()
+                    || process_lists.len() > 1
+                    || self.comments_in(self.doc.extent(node)).next().is_some()
+                {
+                    return None;
+                }
+                let content = match process_lists.first() {
+                    Some(list) => self.flat(list)?,
+                    None => String::new(),
+                };
+                Some(format!("{}{{{}}}", name.0, content))
+            }
+            ASTNode::ProcessList { processes, .. } => Some(
+                processes
+                    .iter()
+                    .map(|process| self.flat(process))
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", "),
+            ),
+            ASTNode::Rule { .. } => None,
+        }
+    }
+
+    /// An operand of an arithmetic operator, parenthesized if it is an operation with a
+    /// precedence for which `paren` holds.
+    fn operand(&self, operand: &ASTNode, paren: impl Fn(u8) -> bool) -> Option<String> {
+        let text = self.flat(operand)?;
+        match operand {
+            ASTNode::Atom {
+                name: (AtomName::Operator(op), _),
+                args,
+                ..
+            } if args.len() == 2 && op.is_arithmetic() && paren(precedence(op)) => {
+                Some(format!("({})", text))
+            }
+            _ => Some(text),
+        }
+    }
+
+    fn atom_name(&self, name: &(AtomName, Span)) -> String {
+        match &name.0 {
+            // string literals are read as plain atoms without their quotes and escapes
+            AtomName::Plain(_) if self.doc.slice(name.1).starts_with('"') => self.doc.slice(name.1),
+            AtomName::Plain(name) | AtomName::Keyword(name) => name.clone(),
+            AtomName::Operator(op) => op.to_string(),
+            // keep the literals as written, e.g. in hexadecimal
+            AtomName::Int(_) | AtomName::Float(_) => self.doc.slice(name.1),
+            AtomName::Char(c) => format!("'{}'", c),
+        }
+    }
+}
+
+fn precedence(op: &Operator) -> u8 {
+    match op {
+        Operator::IMul | Operator::IDiv | Operator::IMod | Operator::FMul | Operator::FDiv => 2,
+        Operator::IAdd | Operator::ISub | Operator::FAdd | Operator::FSub => 1,
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_format() {
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    };
+    let format = |text: &str| {
+        let (doc, _) = Document::parse(text.to_owned());
+        Formatter::new(&doc, &options).format()
+    };
+
+    let source = "// counter\na(X),b( X ) ,c((1+2)*3).\n\n\n\
+                  m{x.y:-z. /* keep */}.\n\
+                  inc@@n(X):-int(X)|n(X+1). // step\n\
+                  long_rule_name(Alpha, Beta, Gamma) :- alpha(Alpha), beta(Beta), gamma(Gamma), delta.\n\
+                  p(A /* inline */ ).";
+    let expected = "// counter\na(X), b(X), c((1 + 2) * 3).\n\n\
+                    m{\n    x.\n    y :- z. /* keep */\n}.\n\
+                    inc @@ n(X) :- int(X) | n(X + 1). // step\n\
+                    long_rule_name(Alpha, Beta, Gamma) :-\n    alpha(Alpha), beta(Beta), gamma(Gamma), delta.\n\
+                    p(A /* inline */ ).\n";
+    let formatted = format(source);
+    assert_eq!(formatted, expected);
+    assert_eq!(format(&formatted), formatted);
+    assert_eq!(
+        format("{a, {b}}, {c. d}."),
+        "{a, {b}},\n{\n    c.\n    d.\n}.\n"
+    );
+
+    // comments after the last statement
+    assert_eq!(format("a. // x"), "a. // x\n");
+    assert_eq!(format("a.\n/* x */"), "a.\n/* x */\n");
+    assert_eq!(
+        format("a :- b.\n// after rule\n"),
+        "a :- b.\n// after rule\n"
+    );
+
+    // string literals keep their quotes and escapes
+    assert_eq!(format("a(\"hello world\")."), "a(\"hello world\").\n");
+    assert_eq!(format("a( \"x\\\"y\" )."), "a(\"x\\\"y\").\n");
+}
+
+#[test]
+fn test_format_range() {
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    };
+    let (doc, _) = Document::parse("a( X ),b(X) .\nm{ c:-d . e , f }.".to_owned());
+    let at = |line, character| Position { line, character };
+
+    let edits = format_range(
+        &doc,
+        Range {
+            start: at(0, 0),
+            end: at(0, 3),
+        },
+        &options,
+    )
+    .unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].new_text, "a(X), b(X).");
+    assert_eq!(edits[0].range.end, at(0, 13));
+
+    // only the rule inside the membrane
+    let edits = format_range(
+        &doc,
+        Range {
+            start: at(1, 4),
+            end: at(1, 4),
+        },
+        &options,
+    )
+    .unwrap();
+    assert_eq!(edits[0].new_text, "c :- d.");
+    assert_eq!(
+        (edits[0].range.start, edits[0].range.end),
+        (at(1, 3), at(1, 9))
+    );
+
+    let edits = format_on_type(&doc, at(1, 9), &options).unwrap();
+    assert_eq!(edits[0].new_text, "c :- d.");
+    assert!(format_on_type(&doc, at(1, 8), &options).is_none());
+}
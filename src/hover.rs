@@ -0,0 +1,315 @@
+use lmntalc::ASTNode;
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Range};
+
+use crate::{
+    builtin,
+    document::Document,
+    reference::RefereceMap,
+    symbol::Symbol,
+    syntax::{children, visit_atoms, visit_nodes, Functor},
+    utils::{contains, span_to_range, to_position},
+};
+
+pub fn hover(doc: &Document, ref_map: &RefereceMap, position: Position) -> Option<Hover> {
+    if let Some((atom @ ASTNode::Atom { name, .. }, functor)) = doc.atom_at(position) {
+        let value = if doc.is_string(atom) {
+            format!("string `{}`", doc.slice(name.1))
+        } else {
+            let in_guard = doc.path_at(position).iter().any(|node| {
+                matches!(node, ASTNode::Rule { guard: Some(guard), .. }
+                    if contains(doc.extent(guard), position))
+            });
+            match builtin::lookup(&name.0, in_guard) {
+                Some(builtin) => builtin.markdown(),
+                None => atom_hover(doc, &functor),
+            }
+        };
+        return Some(markdown(value, span_to_range(name.1)));
+    }
+
+    let path = doc.path_at(position);
+    match path.last()? {
+        ASTNode::Link {
+            name,
+            hyperlink,
+            span,
+        } => Some(markdown(
+            link_hover(doc, ref_map, name, *hyperlink, to_position(span.low())),
+            span_to_range(*span),
+        )),
+        membrane @ ASTNode::Membrane { name, .. } if path.len() > 1 => Some(markdown(
+            membrane_hover(membrane),
+            if name.1.is_empty() {
+                span_to_range(doc.extent(membrane))
+            } else {
+                span_to_range(name.1)
+            },
+        )),
+        rule @ ASTNode::Rule { name, .. } => Some(markdown(
+            rule_hover(doc, rule),
+            if name.1.is_empty() {
+                span_to_range(doc.extent(rule))
+            } else {
+                span_to_range(name.1)
+            },
+        )),
+        _ => None,
+    }
+}
+
+fn markdown(value: String, range: Range) -> Hover {
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(range),
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    format!("{} {}{}", count, word, if count == 1 { "" } else { "s" })
+}
+
+/// Collapse the source text of a node into a single line.
+fn summary(doc: &Document, node: &ASTNode) -> String {
+    doc.slice(doc.extent(node))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn atom_hover(doc: &Document, functor: &Functor) -> String {
+    let mut consumers = 0;
+    let mut producers = 0;
+    visit_nodes(&doc.ast, &mut |node| {
+        if let ASTNode::Rule { head, body, .. } = node {
+            let mut found = false;
+            visit_atoms(head, &mut |atom, f| {
+                found |= f == *functor && !doc.is_string(atom)
+            });
+            consumers += found as usize;
+            if let Some(body) = body {
+                let mut found = false;
+                visit_atoms(body, &mut |atom, f| {
+                    found |= f == *functor && !doc.is_string(atom)
+                });
+                producers += found as usize;
+            }
+        }
+    });
+    format!(
+        "atom `{}`\n\n---\n\nconsumed by {}, produced by {}",
+        functor,
+        plural(consumers, "rule"),
+        plural(producers, "rule"),
+    )
+}
+
+fn link_hover(
+    doc: &Document,
+    ref_map: &RefereceMap,
+    name: &str,
+    hyperlink: bool,
+    start: Position,
+) -> String {
+    let kind = if hyperlink { "hyperlink" } else { "link" };
+    let title = format!("{} `{}{}`", kind, if hyperlink { "!" } else { "" }, name);
+    let partners = ref_map.query_partners(start.line, start.character);
+    let checks = ref_map.query_checks(start.line, start.character);
+    if partners.is_empty() && checks.is_empty() {
+        return format!("{}\n\n---\n\nno other occurrence", title);
+    }
+
+    let mut sections = vec![];
+    let connected = if hyperlink {
+        "shared with"
+    } else {
+        "connected to"
+    };
+    for (heading, symbols) in [(connected, partners), ("checked by", checks)] {
+        if !symbols.is_empty() {
+            sections.push(format!("{}:\n\n{}", heading, locations(doc, &symbols)));
+        }
+    }
+    format!("{}\n\n---\n\n{}", title, sections.join("\n\n"))
+}
+
+/// The occurrences of a link as a list in the order of their positions.
+fn locations(doc: &Document, symbols: &[Symbol]) -> String {
+    let mut positions = symbols
+        .iter()
+        .map(|symbol| Range::from(*symbol).start)
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|position| (position.line, position.character));
+    positions
+        .into_iter()
+        .map(|position| {
+            let location = format!(
+                "line {}, column {}",
+                position.line + 1,
+                position.character + 1
+            );
+            match doc.link_argument(position) {
+                Some((functor, index)) => {
+                    format!("- argument {} of `{}` at {}", index, functor, location)
+                }
+                None => format!("- {}", location),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn membrane_hover(membrane: &ASTNode) -> String {
+    fn count(node: &ASTNode, atoms: &mut usize, membranes: &mut usize) {
+        match node {
+            ASTNode::Membrane { .. } => *membranes += 1,
+            ASTNode::Atom { .. } => {
+                *atoms += 1;
+                children(node)
+                    .into_iter()
+                    .for_each(|arg| count(arg, atoms, membranes));
+            }
+            ASTNode::ProcessList { .. } => children(node)
+                .into_iter()
+                .for_each(|process| count(process, atoms, membranes)),
+            _ => {}
+        }
+    }
+
+    let ASTNode::Membrane {
+        name,
+        process_lists,
+        rules,
+        ..
+    } = membrane
+    else {
+        unreachable!()
+    };
+    let (mut atoms, mut membranes) = (0, 0);
+    for process_list in process_lists {
+        count(process_list, &mut atoms, &mut membranes);
+    }
+    format!(
+        "membrane{}\n\n---\n\n{}, {}, {}",
+        if name.0.is_empty() {
+            String::new()
+        } else {
+            format!(" `{}`", name.0)
+        },
+        plural(atoms, "atom"),
+        plural(membranes, "membrane"),
+        plural(rules.len(), "rule"),
+    )
+}
+
+fn rule_hover(doc: &Document, rule: &ASTNode) -> String {
+    let ASTNode::Rule {
+        name,
+        head,
+        propagation,
+        guard,
+        body,
+        ..
+    } = rule
+    else {
+        unreachable!()
+    };
+    let mut lhs = summary(doc, head);
+    if let Some(propagation) = propagation {
+        lhs = format!("{} \\ {}", summary(doc, propagation), lhs);
+    }
+    let rhs = body
+        .as_ref()
+        .map(|body| summary(doc, body))
+        .unwrap_or_default();
+    format!(
+        "rule{}\n\n---\n\n```lmntal\n{} → {}\n```{}",
+        if name.1.is_empty() {
+            String::new()
+        } else {
+            format!(" `{}`", name.0)
+        },
+        lhs,
+        if rhs.is_empty() { "(empty)" } else { &rhs },
+        guard
+            .as_ref()
+            .map(|guard| format!("\n\nguard: `{}`", summary(doc, guard)))
+            .unwrap_or_default()
+    )
+}
+
+#[test]
+fn test_hover() {
+    use crate::document::analyzed;
+
+    let (doc, _, ref_map) = analyzed("a(X), {b(X)}. a(Y) :- c(Y).");
+    let value = |character| match hover(&doc, &ref_map, Position { line: 0, character }) {
+        Some(Hover {
+            contents: HoverContents::Markup(content),
+            ..
+        }) => content.value,
+        _ => String::new(),
+    };
+
+    assert!(value(0).contains("`a/1`") && value(0).contains("consumed by 1 rule,"));
+    assert!(value(2).contains("argument 1 of `b/1` at line 1, column 10"));
+    assert!(value(6).contains("1 atom, 0 membranes, 0 rules"));
+    assert!(value(19).contains("a(Y) → c(Y)"));
+
+    // guards check links rather than connect them
+    let (doc, _, ref_map) = analyzed("a(X) :- int(X) | b(X).");
+    let Some(Hover {
+        contents: HoverContents::Markup(content),
+        ..
+    }) = hover(&doc, &ref_map, Position::new(0, 2))
+    else {
+        panic!()
+    };
+    assert!(content.value.ends_with(
+        "connected to:\n\n- argument 1 of `b/1` at line 1, column 20\n\n\
+         checked by:\n\n- argument 1 of `int/1` at line 1, column 13"
+    ));
+
+    // partners are listed in the order of their positions
+    let (doc, _, ref_map) = analyzed("a(!H).\n\n\n\n\n\n\n\n\nc(!H), b(!H).");
+    let Some(Hover {
+        contents: HoverContents::Markup(content),
+        ..
+    }) = hover(&doc, &ref_map, Position::new(0, 3))
+    else {
+        panic!()
+    };
+    assert!(content.value.ends_with(
+        "- argument 1 of `c/1` at line 10, column 3\n- argument 1 of `b/1` at line 10, column 10"
+    ));
+
+    let (doc, _) = Document::parse("a(X) :- X =:= 1 | b(X).".to_owned());
+    let ref_map = RefereceMap::new(vec![], vec![], vec![]);
+    let value = hover(
+        &doc,
+        &ref_map,
+        Position {
+            line: 0,
+            character: 11,
+        },
+    );
+    assert!(
+        matches!(value, Some(Hover { contents: HoverContents::Markup(content), .. })
+        if content.value.contains("+X =:= +Y"))
+    );
+
+    // `mod` is only built in within guards
+    let (doc, _) = Document::parse("mod(X). a(X) :- int(X), mod(X, 2, Y) | b(Y).".to_owned());
+    let ref_map = RefereceMap::new(vec![], vec![], vec![]);
+    let value = |character| match hover(&doc, &ref_map, Position { line: 0, character }) {
+        Some(Hover {
+            contents: HoverContents::Markup(content),
+            ..
+        }) => content.value,
+        _ => String::new(),
+    };
+    assert!(value(0).starts_with("atom `mod/1`"));
+    assert!(value(24).contains("mod(+X, +Y, -Z)"));
+}
@@ -0,0 +1,116 @@
+use lmntalc::{frontend::ast::AtomName, ASTNode};
+use tower_lsp::lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Position, Range};
+
+use crate::{document::Document, reference::RefereceMap, syntax::visit_nodes, utils::to_position};
+
+/// Hints in the range for the argument the other end of each link is connected to, such as
+/// `X → append/3#2`.
+///
+/// With `nested_arity`, nested atoms also get their arity, which counts the implicit last
+/// argument connected to the enclosing atom.
+pub fn inlay_hints(
+    doc: &Document,
+    ref_map: &RefereceMap,
+    range: Range,
+    nested_arity: bool,
+) -> Vec<InlayHint> {
+    let in_range = |position| range.start <= position && position <= range.end;
+    let mut hints = vec![];
+    visit_nodes(&doc.ast, &mut |node| match node {
+        ASTNode::Link {
+            hyperlink: false,
+            span,
+            ..
+        } => {
+            let end = to_position(span.high());
+            if !in_range(end) {
+                return;
+            }
+            let start = to_position(span.low());
+            // guards only check the link, the partner is the other end outside of them
+            let partner = match ref_map
+                .query_partners(start.line, start.character)
+                .as_slice()
+            {
+                [partner] => Range::from(*partner).start,
+                _ => return,
+            };
+            if let Some((functor, index)) = doc.link_argument(partner) {
+                hints.push(hint(end, format!("→ {}#{}", functor, index), None));
+            }
+        }
+        ASTNode::Atom { args, .. } if nested_arity => {
+            for arg in args {
+                if let ASTNode::Atom {
+                    name: (AtomName::Plain(_) | AtomName::Char(_), span),
+                    args,
+                    ..
+                } = arg
+                {
+                    let end = to_position(span.high());
+                    if in_range(end) {
+                        hints.push(hint(
+                            end,
+                            format!("/{}", args.len() + 1),
+                            Some(InlayHintKind::TYPE),
+                        ));
+                    }
+                }
+            }
+        }
+        _ => {}
+    });
+    hints
+}
+
+fn hint(position: Position, label: String, kind: Option<InlayHintKind>) -> InlayHint {
+    InlayHint {
+        position,
+        label: InlayHintLabel::String(label),
+        kind,
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(kind.is_none()),
+        padding_right: None,
+        data: None,
+    }
+}
+
+#[test]
+fn test_inlay_hints() {
+    use crate::document::analyzed;
+
+    let (doc, _, ref_map) = analyzed("a(X, f(g)), append(Y, X, !H), b(Y, !H).");
+    let range = Range {
+        start: Position::new(0, 0),
+        end: Position::new(0, 40),
+    };
+    let labels = |nested_arity| {
+        inlay_hints(&doc, &ref_map, range, nested_arity)
+            .into_iter()
+            .map(|hint| {
+                let InlayHintLabel::String(label) = hint.label else {
+                    unreachable!()
+                };
+                (hint.position.character, label)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        labels(false),
+        vec![
+            (3, "→ append/3#2".to_owned()),
+            (20, "→ b/2#1".to_owned()),
+            (23, "→ a/2#1".to_owned()),
+            (33, "→ append/3#1".to_owned()),
+        ]
+    );
+    assert_eq!(labels(true).len(), 6);
+
+    // the guard does not hide the partner of `X`
+    let (doc, _, ref_map) = analyzed("a(X, Y) :- int(X) | b(X, Y).");
+    let hints = inlay_hints(&doc, &ref_map, range, false);
+    assert_eq!(hints.len(), 4);
+    assert!(matches!(&hints[0].label, InlayHintLabel::String(label) if label == "→ b/2#1"));
+}
@@ -1,10 +1,22 @@
 pub mod analysis;
 pub mod backend;
+pub mod builtin;
 pub mod capabilities;
+pub mod code_action;
+pub mod completion;
 pub mod config;
 pub mod diagnostics;
+pub mod document;
+pub mod folding_range;
+pub mod format;
+pub mod hover;
+pub mod inlay_hint;
 pub mod reference;
+pub mod rename;
+pub mod selection_range;
+pub mod signature_help;
 pub mod symbol;
+pub mod syntax;
 pub mod utils;
 
 use backend::Backend;
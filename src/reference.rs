@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use lmntalc::util::Span;
 
@@ -8,12 +8,17 @@ use crate::symbol::Symbol;
 pub struct RefereceMap {
     symbol_seq: Vec<Symbol>,
     references: HashMap<usize, Vec<usize>>,
+    /// Maps every symbol in a group to the first occurrence of the group.
+    definitions: HashMap<usize, usize>,
+    /// Symbols checking a link in a guard, which are not partners of the link.
+    checks: HashSet<usize>,
 }
 
 impl RefereceMap {
-    pub fn new(refs: Vec<Vec<Span>>, normal_symbol: Vec<Span>) -> Self {
+    pub fn new(refs: Vec<Vec<Span>>, normal_symbol: Vec<Span>, checks: Vec<Span>) -> Self {
         let mut symbol_seq = Vec::new();
         let mut references = HashMap::new();
+        let mut definitions = HashMap::new();
 
         for group in &refs {
             for span in group {
@@ -37,11 +42,17 @@ impl RefereceMap {
             .collect::<HashMap<_, _>>();
 
         for group in &refs {
+            let first = group
+                .first()
+                .map(|span| *map.get(&Symbol::new(*span)).unwrap());
             // insert theme alternatevely
             // [1, 2, 3] => [1, [2, 3]], [2, [1, 3]], [3, [1, 2]]
             for (i, span) in group.iter().enumerate() {
                 let symbol = Symbol::new(*span);
                 let index = *map.get(&symbol).unwrap();
+                if let Some(first) = first {
+                    definitions.insert(index, first);
+                }
                 let refs = references.entry(index).or_insert_with(Vec::new);
                 for (j, other_span) in group.iter().enumerate() {
                     if i != j {
@@ -53,9 +64,16 @@ impl RefereceMap {
             }
         }
 
+        let checks = checks
+            .iter()
+            .filter_map(|span| map.get(&Symbol::new(*span)).copied())
+            .collect();
+
         Self {
             symbol_seq,
             references,
+            definitions,
+            checks,
         }
     }
 
@@ -63,12 +81,41 @@ impl RefereceMap {
         find(line, col, &self.symbol_seq).map(|i| self.symbol_seq[i])
     }
 
+    /// Returns the first occurrence of the group the symbol at the given position belongs to.
+    pub fn query_definition(&self, line: u32, col: u32) -> Option<Symbol> {
+        let index = find(line, col, &self.symbol_seq)?;
+        let def = self.definitions.get(&index)?;
+        Some(self.symbol_seq[*def])
+    }
+
     pub fn query_references(&self, line: u32, col: u32) -> Option<Vec<Symbol>> {
         let index = find(line, col, &self.symbol_seq)?;
         let refs = self.references.get(&index)?;
         Some(refs.iter().map(|&i| self.symbol_seq[i]).collect())
     }
 
+    /// Returns the references of the symbol at the given position which it is connected to,
+    /// leaving out the checks in guards.
+    pub fn query_partners(&self, line: u32, col: u32) -> Vec<Symbol> {
+        self.query_group(line, col, false)
+    }
+
+    /// Returns the references of the symbol at the given position which check it in guards.
+    pub fn query_checks(&self, line: u32, col: u32) -> Vec<Symbol> {
+        self.query_group(line, col, true)
+    }
+
+    fn query_group(&self, line: u32, col: u32, checks: bool) -> Vec<Symbol> {
+        let Some(refs) = find(line, col, &self.symbol_seq).and_then(|i| self.references.get(&i))
+        else {
+            return vec![];
+        };
+        refs.iter()
+            .filter(|i| self.checks.contains(i) == checks)
+            .map(|&i| self.symbol_seq[i])
+            .collect()
+    }
+
     pub fn query_references_with_self(&self, line: u32, col: u32) -> Option<Vec<Symbol>> {
         let index = find(line, col, &self.symbol_seq)?;
         if let Some(refs) = self.references.get(&index) {
@@ -82,7 +129,7 @@ impl RefereceMap {
 }
 
 /// Find if there is a symbol at the given position, and return the index of the symbol in the symbol sequence.
-fn find(line: u32, col: u32, refs: &Vec<Symbol>) -> Option<usize> {
+fn find(line: u32, col: u32, refs: &[Symbol]) -> Option<usize> {
     if refs.is_empty() {
         return None;
     }
@@ -146,3 +193,25 @@ fn test_find() {
     assert_eq!(find(1, 4, &refs), Some(3));
     assert_eq!(find(1, 5, &refs), Some(3));
 }
+
+#[test]
+fn test_query_definition() {
+    use lmntalc::util::Pos;
+
+    let span = |line: u32, col: u32| {
+        Span::new(
+            Pos::new(line * 10 + col, line, col),
+            Pos::new(line * 10 + col + 1, line, col + 1),
+        )
+    };
+    let map = RefereceMap::new(
+        vec![vec![span(0, 2), span(1, 4)], vec![span(0, 5), span(1, 0)]],
+        vec![span(0, 0), span(2, 0)],
+        vec![],
+    );
+
+    assert_eq!(map.query_definition(0, 2), Some(Symbol::new(span(0, 2))));
+    assert_eq!(map.query_definition(1, 4), Some(Symbol::new(span(0, 2))));
+    assert_eq!(map.query_definition(1, 0), Some(Symbol::new(span(0, 5))));
+    assert_eq!(map.query_definition(2, 0), None);
+}
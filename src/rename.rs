@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use lmntalc::{frontend::ast::AtomName, ASTNode};
+use tower_lsp::{
+    jsonrpc::{Error, Result},
+    lsp_types::{
+        LinkedEditingRanges, Position, PrepareRenameResponse, TextEdit, Url, WorkspaceEdit,
+    },
+};
+
+use crate::{
+    document::Document,
+    reference::RefereceMap,
+    syntax::{
+        is_atom_name, is_link_name, link_name_range, link_names, link_occurrences, link_scope,
+        scope_kind, visit_atoms, visit_nodes, Functor,
+    },
+    utils::{contains, span_to_range, to_position},
+};
+
+pub fn prepare_rename(doc: &Document, position: Position) -> Option<PrepareRenameResponse> {
+    match doc.path_at(position).last()? {
+        ASTNode::Link { name, span, .. } => Some(PrepareRenameResponse::RangeWithPlaceholder {
+            range: link_name_range(span_to_range(*span), name),
+            placeholder: name.clone(),
+        }),
+        atom @ ASTNode::Atom {
+            name: (AtomName::Plain(name), span),
+            ..
+        } if contains(*span, position) && doc.is_plain_atom(atom) => {
+            Some(PrepareRenameResponse::RangeWithPlaceholder {
+                range: span_to_range(*span),
+                placeholder: name.clone(),
+            })
+        }
+        ASTNode::Membrane { name, .. } | ASTNode::Rule { name, .. }
+            if !name.1.is_empty() && contains(name.1, position) =>
+        {
+            Some(PrepareRenameResponse::RangeWithPlaceholder {
+                range: span_to_range(name.1),
+                placeholder: name.0.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The functor of the atom at the position, if it is a plain atom.
+pub fn functor_at(doc: &Document, position: Position) -> Option<Functor> {
+    let (atom, functor) = doc.atom_at(position)?;
+    doc.is_plain_atom(atom).then_some(functor)
+}
+
+pub fn check_atom_name(new_name: &str) -> Result<()> {
+    check_name(new_name, "atom")
+}
+
+fn check_name(new_name: &str, kind: &str) -> Result<()> {
+    if is_atom_name(new_name) {
+        Ok(())
+    } else {
+        Err(Error::invalid_params(format!(
+            "`{}` is not a valid {} name, {} names start with a lowercase letter and cannot be a keyword",
+            new_name, kind, kind
+        )))
+    }
+}
+
+/// Warn if renaming the rule at the position collides with another rule in the same membrane.
+///
+/// The rename is still allowed, but SLIM traces identify rules by their names.
+pub fn rule_collision(doc: &Document, position: Position, new_name: &str) -> Option<String> {
+    let path = doc.path_at(position);
+    let Some(ASTNode::Rule { name, .. }) = path.last() else {
+        return None;
+    };
+    if name.1.is_empty() || !contains(name.1, position) || name.0 == new_name {
+        return None;
+    }
+    let Some(ASTNode::Membrane { rules, .. }) = path.iter().rev().nth(1) else {
+        return None;
+    };
+    rules
+        .iter()
+        .any(|rule| matches!(rule, ASTNode::Rule { name, .. } if !name.1.is_empty() && name.0 == new_name))
+        .then(|| format!("Rule `{}` already exists in this membrane", new_name))
+}
+
+/// Rename every atom with the functor in the document.
+pub fn rename_functor(doc: &Document, functor: &Functor, new_name: &str) -> Vec<TextEdit> {
+    let mut edits = vec![];
+    visit_atoms(&doc.ast, &mut |atom, atom_functor| {
+        if let ASTNode::Atom { name, .. } = atom {
+            if doc.is_plain_atom(atom) && atom_functor == *functor {
+                edits.push(TextEdit {
+                    range: span_to_range(name.1),
+                    new_text: new_name.to_owned(),
+                });
+            }
+        }
+    });
+    edits
+}
+
+/// The ranges of the names of every occurrence of the link at the position, which are edited
+/// together.
+pub fn linked_editing_ranges(
+    doc: &Document,
+    ref_map: &RefereceMap,
+    position: Position,
+) -> Option<LinkedEditingRanges> {
+    let Some(ASTNode::Link { name, span, .. }) = doc.path_at(position).pop() else {
+        return None;
+    };
+    let start = to_position(span.low());
+    let ranges = ref_map
+        .query_references_with_self(start.line, start.character)?
+        .into_iter()
+        .map(|symbol| link_name_range(symbol.into(), name))
+        .collect();
+    Some(LinkedEditingRanges {
+        ranges,
+        word_pattern: Some("[A-Z][A-Za-z0-9_]*".to_owned()),
+    })
+}
+
+pub fn rename(
+    uri: &Url,
+    doc: &Document,
+    ref_map: &RefereceMap,
+    position: Position,
+    new_name: &str,
+) -> Result<Option<WorkspaceEdit>> {
+    let path = doc.path_at(position);
+    match path.last() {
+        Some(ASTNode::Link {
+            name, hyperlink, ..
+        }) => {
+            let new_name = if *hyperlink {
+                new_name.strip_prefix('!').unwrap_or(new_name)
+            } else {
+                new_name
+            };
+            if !is_link_name(new_name) {
+                return Err(Error::invalid_params(format!(
+                    "`{}` is not a valid link name, link names start with an uppercase letter",
+                    new_name
+                )));
+            }
+
+            let occurrences = link_occurrences(ref_map, &path);
+
+            let scope = link_scope(doc, &path, &occurrences, *hyperlink);
+            if new_name != name && link_names(scope, *hyperlink).contains(new_name) {
+                return Err(Error::invalid_params(format!(
+                    "{} `{}` already exists in this {}",
+                    if *hyperlink { "Hyperlink" } else { "Link" },
+                    new_name,
+                    scope_kind(scope)
+                )));
+            }
+
+            let edits = occurrences
+                .into_iter()
+                .map(|range| TextEdit {
+                    range: link_name_range(range, name),
+                    new_text: new_name.to_owned(),
+                })
+                .collect();
+            Ok(Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }))
+        }
+        Some(ASTNode::Membrane { name, .. })
+            if !name.1.is_empty() && contains(name.1, position) =>
+        {
+            check_name(new_name, "membrane")?;
+            // rules match membranes by their names, so rename all of them together
+            let mut edits = vec![];
+            visit_nodes(&doc.ast, &mut |node| {
+                if let ASTNode::Membrane { name: other, .. } = node {
+                    if !other.1.is_empty() && other.0 == name.0 {
+                        edits.push(TextEdit {
+                            range: span_to_range(other.1),
+                            new_text: new_name.to_owned(),
+                        });
+                    }
+                }
+            });
+            Ok(Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }))
+        }
+        Some(ASTNode::Rule { name, .. }) if !name.1.is_empty() && contains(name.1, position) => {
+            check_name(new_name, "rule")?;
+            Ok(Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(
+                    uri.clone(),
+                    vec![TextEdit {
+                        range: span_to_range(name.1),
+                        new_text: new_name.to_owned(),
+                    }],
+                )])),
+                ..Default::default()
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[test]
+fn test_rename_link() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _, ref_map) = analyzed("a(X, Y), b(X, Y). c(X) :- d(X, Y), e(Y).");
+    let at = |character| Position { line: 0, character };
+
+    // `Y` already occurs in the rule
+    assert!(rename(&uri, &doc, &ref_map, at(20), "Y").is_err());
+    assert!(rename(&uri, &doc, &ref_map, at(20), "lower").is_err());
+
+    let edit = rename(&uri, &doc, &ref_map, at(20), "Z").unwrap().unwrap();
+    let mut edits = edit.changes.unwrap().remove(&uri).unwrap();
+    edits.sort_by_key(|edit| edit.range.start);
+    assert_eq!(
+        edits
+            .iter()
+            .map(|e| e.range.start.character)
+            .collect::<Vec<_>>(),
+        vec![20, 28]
+    );
+
+    // the top level `Y` is not in the scope of the rule
+    assert!(rename(&uri, &doc, &ref_map, at(2), "Z").is_ok());
+    assert!(rename(&uri, &doc, &ref_map, at(2), "Y").is_err());
+}
+
+#[test]
+fn test_rename_unpaired_link() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _, ref_map) = analyzed("a(X), b(X), c(X). d(F). e(Y) :- f(Y, Y, Y), g(F).");
+    let starts = |character| {
+        let edit = rename(&uri, &doc, &ref_map, Position::new(0, character), "Z")
+            .unwrap()
+            .unwrap();
+        let mut starts = edit.changes.unwrap()[&uri]
+            .iter()
+            .map(|e| e.range.start.character)
+            .collect::<Vec<_>>();
+        starts.sort();
+        starts
+    };
+
+    // a link occurring three times
+    assert_eq!(starts(2), vec![2, 8, 14]);
+    assert_eq!(starts(37), vec![26, 34, 37, 40]);
+    // a free link, the `F` in the rule is not in the same scope
+    assert_eq!(starts(20), vec![20]);
+}
+
+#[test]
+fn test_rename_functor() {
+    let (doc, _) = Document::parse("a(f(X)), f(X, Y), f(Y), int(Y). f(A) :- g(A).".to_owned());
+    let at = |character| Position { line: 0, character };
+
+    let functor = functor_at(&doc, at(2)).unwrap();
+    assert_eq!(functor.to_string(), "f/2");
+    let edits = rename_functor(&doc, &functor, "h");
+    assert_eq!(
+        edits
+            .iter()
+            .map(|e| e.range.start.character)
+            .collect::<Vec<_>>(),
+        vec![2, 9]
+    );
+
+    assert_eq!(functor_at(&doc, at(18)).unwrap().to_string(), "f/1");
+    assert_eq!(
+        rename_functor(&doc, &functor_at(&doc, at(18)).unwrap(), "h").len(),
+        2
+    );
+    // keywords are not renamable
+    assert!(functor_at(&doc, at(24)).is_none());
+    assert!(check_atom_name("int").is_err());
+    // string literals are not atoms to rename
+    let (doc, _) = Document::parse("\"b\", a(X), b.".to_owned());
+    assert!(functor_at(&doc, at(1)).is_none());
+    let functor = functor_at(&doc, at(12)).unwrap();
+    assert_eq!(rename_functor(&doc, &functor, "c").len(), 1);
+}
+
+#[test]
+fn test_rename_membrane_and_rule() {
+    use crate::document::analyzed;
+
+    let uri = Url::parse("file:///test.lmn").unwrap();
+    let (doc, _, ref_map) = analyzed("m{x}, m{y}. r @@ a :- b. s @@ b :- c.");
+    let at = |character| Position { line: 0, character };
+
+    let edit = rename(&uri, &doc, &ref_map, at(0), "n").unwrap().unwrap();
+    assert_eq!(edit.changes.unwrap()[&uri].len(), 2);
+
+    assert!(rename(&uri, &doc, &ref_map, at(12), "Upper").is_err());
+    assert!(rename(&uri, &doc, &ref_map, at(12), "t").unwrap().is_some());
+    assert!(rule_collision(&doc, at(12), "t").is_none());
+    assert!(rule_collision(&doc, at(12), "s").is_some());
+}
+
+#[test]
+fn test_linked_editing_ranges() {
+    use crate::document::analyzed;
+
+    let (doc, _, ref_map) = analyzed("a(X), b(X). c(!H), d(!H).");
+    let at = |character| Position { line: 0, character };
+    let starts = |character| {
+        let mut starts = linked_editing_ranges(&doc, &ref_map, at(character))
+            .unwrap()
+            .ranges
+            .into_iter()
+            .map(|range| (range.start.character, range.end.character))
+            .collect::<Vec<_>>();
+        starts.sort();
+        starts
+    };
+
+    assert_eq!(starts(8), vec![(2, 3), (8, 9)]);
+    // the `!` is not part of the name
+    assert_eq!(starts(16), vec![(15, 16), (22, 23)]);
+    assert!(linked_editing_ranges(&doc, &ref_map, at(0)).is_none());
+}
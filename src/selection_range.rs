@@ -0,0 +1,77 @@
+use lmntalc::ASTNode;
+use tower_lsp::lsp_types::{Position, Range, SelectionRange};
+
+use crate::{
+    document::Document,
+    utils::{contains, span_to_range},
+};
+
+/// The selection ranges at each position, growing from the innermost node containing it to the
+/// whole document along the syntax tree.
+pub fn selection_ranges(doc: &Document, positions: &[Position]) -> Vec<SelectionRange> {
+    positions
+        .iter()
+        .map(|&position| {
+            let path = doc.path_at(position);
+            let mut ranges = path
+                .iter()
+                .map(|node| span_to_range(doc.extent(node)))
+                .collect::<Vec<_>>();
+            match path.last() {
+                Some(ASTNode::Atom { name, .. }) if contains(name.1, position) => {
+                    ranges.push(span_to_range(name.1))
+                }
+                Some(ASTNode::Membrane { name, .. } | ASTNode::Rule { name, .. })
+                    if !name.1.is_empty() && contains(name.1, position) =>
+                {
+                    ranges.push(span_to_range(name.1))
+                }
+                _ => {}
+            }
+            ranges.dedup();
+
+            let mut selection: Option<SelectionRange> = None;
+            for range in ranges {
+                selection = Some(SelectionRange {
+                    range,
+                    parent: selection.map(Box::new),
+                });
+            }
+            selection.unwrap_or(SelectionRange {
+                range: Range {
+                    start: position,
+                    end: position,
+                },
+                parent: None,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_selection_ranges() {
+    let text = "a.\n{r @@ f(g(X)) :- int(Y) | h(X, Y), i.}.";
+    let (doc, _) = Document::parse(text.to_owned());
+    let ranges = selection_ranges(&doc, &[Position::new(1, 10)]);
+    let mut selection = Some(&ranges[0]);
+    let mut texts = vec![];
+    while let Some(range) = selection {
+        let (low, high) = (
+            doc.offset_at(range.range.start),
+            doc.offset_at(range.range.end),
+        );
+        texts.push(text.chars().skip(low).take(high - low).collect::<String>());
+        selection = range.parent.as_deref();
+    }
+    assert_eq!(
+        texts,
+        vec![
+            "X",
+            "g(X)",
+            "f(g(X))",
+            "r @@ f(g(X)) :- int(Y) | h(X, Y), i",
+            "{r @@ f(g(X)) :- int(Y) | h(X, Y), i.}",
+            text,
+        ]
+    );
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use lmntalc::frontend::{ast::AtomName, token::TokenKind};
+use tower_lsp::lsp_types::{
+    Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, Position,
+    SignatureHelp, SignatureInformation,
+};
+
+use crate::{builtin, document::Document, syntax::Functor};
+
+/// Show the signatures of the atom whose arguments are being typed at the position.
+///
+/// The parser gives up on the arguments of an atom being typed, so this works on the tokens.
+/// Atoms are shown with every arity they have in `functors`, built-in guards with the modes of
+/// their arguments.
+pub fn signature_help(
+    doc: &Document,
+    position: Position,
+    functors: &HashSet<Functor>,
+) -> Option<SignatureHelp> {
+    let offset = doc.offset_at(position) as u32;
+    // the open parentheses before the position, with the commas after them
+    let mut open: Vec<(usize, u32)> = vec![];
+    for (i, token) in doc.tokens.iter().enumerate() {
+        if token.span.high().offset > offset {
+            break;
+        }
+        match token.kind {
+            TokenKind::LeftParen => open.push((i, 0)),
+            TokenKind::RightParen => {
+                open.pop();
+            }
+            TokenKind::Comma => {
+                if let Some((_, commas)) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            TokenKind::Dot => open.clear(),
+            _ => {}
+        }
+    }
+    let &(paren, commas) = open.last()?;
+    let nested = open.len() > 1;
+
+    let atom = paren.checked_sub(1)?;
+    let builtin = match &doc.tokens[atom].kind {
+        TokenKind::Keyword(name) => builtin::lookup(&AtomName::Keyword(name.clone()), false),
+        TokenKind::Identifier(name) => {
+            builtin::lookup(&AtomName::Plain(name.clone()), in_guard(doc, atom))
+        }
+        _ => None,
+    };
+    if let Some(builtin) = builtin {
+        let signature = SignatureInformation {
+            label: builtin.signature.to_owned(),
+            documentation: Some(markdown(builtin.doc.to_owned())),
+            parameters: Some(
+                builtin
+                    .args
+                    .iter()
+                    .map(|arg| ParameterInformation {
+                        label: ParameterLabel::Simple((*arg).to_owned()),
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: None,
+        };
+        return Some(SignatureHelp {
+            signatures: vec![signature],
+            active_signature: Some(0),
+            active_parameter: Some(commas.min(builtin.args.len() as u32 - 1)),
+        });
+    }
+    let name = match &doc.tokens[atom].kind {
+        TokenKind::Identifier(name) => name.clone(),
+        TokenKind::Char(c) => format!("'{}'", c),
+        _ => return None,
+    };
+
+    // a nested atom is written without its last argument
+    let mut arities = functors
+        .iter()
+        .filter(|functor| functor.name == name && functor.arity > nested as usize)
+        .map(|functor| functor.arity)
+        .collect::<Vec<_>>();
+    arities.sort();
+    let signatures = arities
+        .iter()
+        .map(|&arity| {
+            let args = (1..=arity - nested as usize)
+                .map(|i| format!("X{}", i))
+                .collect::<Vec<_>>();
+            let functor = Functor {
+                name: name.clone(),
+                arity,
+            };
+            SignatureInformation {
+                label: format!("{}({})", name, args.join(", ")),
+                documentation: Some(markdown(if nested {
+                    format!(
+                        "`{}`, whose last argument is connected to the enclosing atom",
+                        functor
+                    )
+                } else {
+                    format!("`{}`", functor)
+                })),
+                parameters: Some(
+                    args.into_iter()
+                        .map(|arg| ParameterInformation {
+                            label: ParameterLabel::Simple(arg),
+                            documentation: None,
+                        })
+                        .collect(),
+                ),
+                active_parameter: None,
+            }
+        })
+        .collect::<Vec<_>>();
+    if signatures.is_empty() {
+        return None;
+    }
+
+    // the smallest arity with enough arguments for the ones typed
+    let active_signature = arities
+        .iter()
+        .position(|&arity| arity - nested as usize > commas as usize)
+        .unwrap_or(arities.len() - 1);
+    Some(SignatureHelp {
+        signatures,
+        active_signature: Some(active_signature as u32),
+        active_parameter: Some(commas),
+    })
+}
+
+/// Check if the token at the index is in a guard, between a `:-` and a `|` outside of lists.
+fn in_guard(doc: &Document, index: usize) -> bool {
+    let is_boundary = |kind: &TokenKind| {
+        matches!(
+            kind,
+            TokenKind::Dot | TokenKind::LeftBrace | TokenKind::RightBrace
+        )
+    };
+    let start = doc.tokens[..index]
+        .iter()
+        .rposition(|token| is_boundary(&token.kind))
+        .map_or(0, |i| i + 1);
+    if !doc.tokens[start..index]
+        .iter()
+        .any(|token| token.kind == TokenKind::ColonDash)
+    {
+        return false;
+    }
+    let mut depth = 0;
+    for token in &doc.tokens[index..] {
+        match token.kind {
+            TokenKind::LeftBracket => depth += 1,
+            TokenKind::RightBracket => depth -= 1,
+            TokenKind::Vert if depth <= 0 => return true,
+            ref kind if is_boundary(kind) => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn markdown(value: String) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+#[test]
+fn test_signature_help() {
+    let functors = HashSet::from([
+        Functor {
+            name: "f".to_owned(),
+            arity: 1,
+        },
+        Functor {
+            name: "f".to_owned(),
+            arity: 3,
+        },
+    ]);
+    let help = |text: &str| {
+        let (doc, _) = Document::parse(text.to_owned());
+        let position = doc.position_at(text.chars().count());
+        signature_help(&doc, position, &functors).map(|help| {
+            (
+                help.signatures
+                    .into_iter()
+                    .map(|signature| signature.label)
+                    .collect::<Vec<_>>(),
+                help.active_signature.unwrap(),
+                help.active_parameter.unwrap(),
+            )
+        })
+    };
+
+    assert_eq!(
+        help("a :- f(X, "),
+        Some((vec!["f(X1)".to_owned(), "f(X1, X2, X3)".to_owned()], 1, 1))
+    );
+    assert_eq!(
+        help("a(g(Y), f(X, "),
+        Some((vec!["f(X1, X2)".to_owned()], 0, 1))
+    );
+    assert_eq!(
+        help("a(X) :- int("),
+        Some((vec!["int(+X)".to_owned()], 0, 0))
+    );
+    // `mod` is only built in within guards
+    let (doc, _) = Document::parse("a(X, Y) :- mod(X, 2, Z) | b(Y, Z).".to_owned());
+    let guard = signature_help(&doc, Position::new(0, 18), &functors).unwrap();
+    assert_eq!(guard.signatures[0].label, "mod(+X, +Y, -Z)");
+    assert_eq!(guard.active_parameter, Some(1));
+    assert_eq!(help("a(X) :- b(X, Y), mod(Y, "), None);
+    assert_eq!(help("f(X). g("), None);
+    assert_eq!(help("f(X)"), None);
+}
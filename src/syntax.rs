@@ -0,0 +1,272 @@
+use std::{collections::HashSet, fmt::Display};
+
+use lmntalc::{
+    frontend::{ast::AtomName, token::KEYWORD},
+    ASTNode,
+};
+use tower_lsp::lsp_types::{Position, Range};
+
+use crate::{
+    document::Document,
+    reference::RefereceMap,
+    utils::{contains, span_to_range, to_position},
+};
+
+/// The direct children of a node, in source order.
+pub fn children(node: &ASTNode) -> Vec<&ASTNode> {
+    match node {
+        ASTNode::Rule {
+            head,
+            propagation,
+            guard,
+            body,
+            ..
+        } => {
+            let mut children = vec![];
+            if let Some(propagation) = propagation {
+                children.push(propagation.as_ref());
+            }
+            children.push(head.as_ref());
+            if let Some(guard) = guard {
+                children.push(guard.as_ref());
+            }
+            if let Some(body) = body {
+                children.push(body.as_ref());
+            }
+            children
+        }
+        ASTNode::ProcessList { processes, .. } => processes.iter().collect(),
+        ASTNode::Membrane {
+            process_lists,
+            rules,
+            ..
+        } => {
+            let mut children = process_lists.iter().chain(rules).collect::<Vec<_>>();
+            children.sort_by_key(|child| child.span().low());
+            children
+        }
+        ASTNode::Atom { args, .. } => args.iter().collect(),
+        ASTNode::Link { .. } | ASTNode::Context { .. } => vec![],
+    }
+}
+
+/// Call `f` on the node and every node under it.
+pub fn visit_nodes<'a>(node: &'a ASTNode, f: &mut impl FnMut(&'a ASTNode)) {
+    f(node);
+    for child in children(node) {
+        visit_nodes(child, f);
+    }
+}
+
+/// The name and the arity of an atom.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Functor {
+    pub name: String,
+    pub arity: usize,
+}
+
+impl Functor {
+    pub fn new(name: &AtomName, arity: usize) -> Self {
+        Self {
+            name: match name {
+                AtomName::Char(c) => format!("'{}'", c),
+                _ => name.to_string(),
+            },
+            arity,
+        }
+    }
+}
+
+impl Display for Functor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.name, self.arity)
+    }
+}
+
+/// Call `f` on every atom under the node along with its functor.
+///
+/// An atom written as an argument of another atom has an implicit last argument connected to
+/// its parent, which is counted in the arity.
+pub fn visit_atoms<'a>(node: &'a ASTNode, f: &mut impl FnMut(&'a ASTNode, Functor)) {
+    fn visit<'a>(node: &'a ASTNode, nested: bool, f: &mut impl FnMut(&'a ASTNode, Functor)) {
+        if let ASTNode::Atom { name, args, .. } = node {
+            f(node, Functor::new(&name.0, args.len() + nested as usize));
+            for arg in args {
+                visit(arg, true, f);
+            }
+        } else {
+            for child in children(node) {
+                visit(child, false, f);
+            }
+        }
+    }
+
+    visit(node, false, f)
+}
+
+/// Check if the name is a valid name for atoms, membranes and rules.
+pub fn is_atom_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORD.contains(&name)
+}
+
+/// Check if the name is a valid link name.
+pub fn is_link_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A name made of `base` and the smallest number not in `taken`.
+pub fn fresh_name(base: &str, taken: &HashSet<&str>) -> String {
+    (1..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|name| !taken.contains(name.as_str()))
+        .unwrap()
+}
+
+/// The range of the name of a link occurrence, excluding the `!` of a hyperlink.
+pub fn link_name_range(occurrence: Range, name: &str) -> Range {
+    Range {
+        start: Position {
+            line: occurrence.end.line,
+            character: occurrence.end.character - name.chars().count() as u32,
+        },
+        end: occurrence.end,
+    }
+}
+
+/// The scope of a link at the end of `path`, which is the innermost rule, or the innermost
+/// membrane containing all of the occurrences of the link.
+///
+/// Hyperlinks are shared by the whole process outside of rules.
+pub fn link_scope<'a>(
+    doc: &Document,
+    path: &[&'a ASTNode],
+    occurrences: &[Range],
+    hyperlink: bool,
+) -> &'a ASTNode {
+    if let Some(rule) = path
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Rule { .. }))
+    {
+        return rule;
+    }
+    if hyperlink {
+        return path[0];
+    }
+    path.iter()
+        .rev()
+        .find(|node| {
+            matches!(node, ASTNode::Membrane { .. })
+                && occurrences
+                    .iter()
+                    .all(|range| contains(doc.extent(node), range.start))
+        })
+        .unwrap_or(&path[0])
+}
+
+/// The ranges of every occurrence of the link at the end of `path`, including itself.
+///
+/// A link occurring once or more than twice has no pair in `ref_map`, so every unpaired
+/// occurrence of its name in the innermost rule, or outside of rules, is taken instead.
+pub fn link_occurrences(ref_map: &RefereceMap, path: &[&ASTNode]) -> Vec<Range> {
+    let Some(ASTNode::Link {
+        name,
+        hyperlink,
+        span,
+    }) = path.last()
+    else {
+        return vec![];
+    };
+    let start = to_position(span.low());
+    if ref_map
+        .query_references(start.line, start.character)
+        .is_some()
+    {
+        return ref_map
+            .query_references_with_self(start.line, start.character)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Range::from)
+            .collect();
+    }
+
+    // links outside of rules are global to the top level
+    let scope = path
+        .iter()
+        .rev()
+        .find(|node| matches!(node, ASTNode::Rule { .. }))
+        .unwrap_or(&path[0]);
+    let mut occurrences = vec![];
+    visit_links(scope, &mut |link| {
+        if let ASTNode::Link {
+            name: other,
+            hyperlink: other_hyperlink,
+            span,
+        } = link
+        {
+            let start = to_position(span.low());
+            if other == name
+                && other_hyperlink == hyperlink
+                && ref_map
+                    .query_references(start.line, start.character)
+                    .is_none()
+            {
+                occurrences.push(span_to_range(*span));
+            }
+        }
+    });
+    occurrences
+}
+
+/// Describe the scope for messages.
+pub fn scope_kind(scope: &ASTNode) -> &'static str {
+    match scope {
+        ASTNode::Rule { .. } => "rule",
+        ASTNode::Membrane { name, .. } if name.0 == "_init" && name.1.is_empty() => "top level",
+        _ => "membrane",
+    }
+}
+
+/// Call `f` on every link and hyperlink occurring in the scope.
+///
+/// Rules inside the scope have their own scopes and are skipped.
+pub fn visit_links<'a>(scope: &'a ASTNode, f: &mut impl FnMut(&'a ASTNode)) {
+    match scope {
+        ASTNode::Link { .. } => f(scope),
+        ASTNode::Membrane { process_lists, .. } => {
+            for process_list in process_lists {
+                visit_links(process_list, f);
+            }
+        }
+        _ => {
+            for child in children(scope) {
+                visit_links(child, f);
+            }
+        }
+    }
+}
+
+/// The names of the links, or the hyperlinks, occurring in the scope.
+///
+/// Rules inside the scope have their own scopes and are skipped.
+pub fn link_names(scope: &ASTNode, hyperlink: bool) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    visit_links(scope, &mut |link| {
+        if let ASTNode::Link {
+            name,
+            hyperlink: is_hyperlink,
+            ..
+        } = link
+        {
+            if *is_hyperlink == hyperlink {
+                names.insert(name.as_str());
+            }
+        }
+    });
+    names
+}
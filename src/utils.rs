@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use lmntalc::util::Pos;
+use lmntalc::util::{Pos, Span};
 use reqwest::ClientBuilder;
 use semver::Version;
 use tower_lsp::lsp_types::Position;
@@ -19,6 +19,11 @@ pub fn span_to_range(span: lmntalc::util::Span) -> tower_lsp::lsp_types::Range {
     }
 }
 
+/// Check if the position is inside the span, both ends are inclusive.
+pub fn contains(span: Span, position: Position) -> bool {
+    to_position(span.low()) <= position && position <= to_position(span.high())
+}
+
 pub async fn check_update() -> Option<Version> {
     let client = ClientBuilder::new()
         .user_agent(concat!(